@@ -6,9 +6,14 @@ use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 // TODO: export json schema from msg code
 
 use bank::msg::{
-    AccountResponse, BalanceResponse, ExecuteMsg, InstantiateMsg, QueryMsg, ReceiveMsg,
+    AccountResponse, BalanceResponse, ConfigResponse, ExecuteMsg, FrozenAccountsResponse,
+    HistoryResponse, InstantiateMsg, JointAccountResponse, ListAccountsResponse,
+    ListProposalsResponse, MigrateMsg, OwnershipResponse, ProposalResponse, QueryMsg,
+    RateLimitResponse, ReceiveMsg, ReservesResponse, RolesResponse, TokensResponse,
+    TreasuryResponse,
 };
-use bank::state::{BalanceData, State};
+use bank::state::{AccountData, PauseState, State};
+use cw20::{AllAllowancesResponse, AllowanceResponse};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -19,9 +24,26 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
-    export_schema(&schema_for!(BalanceData), &out_dir);
+    export_schema(&schema_for!(AccountData), &out_dir);
     export_schema(&schema_for!(AccountResponse), &out_dir);
     export_schema(&schema_for!(BalanceResponse), &out_dir);
     export_schema(&schema_for!(ReceiveMsg), &out_dir);
+    export_schema(&schema_for!(TreasuryResponse), &out_dir);
+    export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(TokensResponse), &out_dir);
+    export_schema(&schema_for!(OwnershipResponse), &out_dir);
+    export_schema(&schema_for!(ListAccountsResponse), &out_dir);
+    export_schema(&schema_for!(AllowanceResponse), &out_dir);
+    export_schema(&schema_for!(AllAllowancesResponse), &out_dir);
+    export_schema(&schema_for!(JointAccountResponse), &out_dir);
+    export_schema(&schema_for!(ProposalResponse), &out_dir);
+    export_schema(&schema_for!(ListProposalsResponse), &out_dir);
+    export_schema(&schema_for!(RolesResponse), &out_dir);
+    export_schema(&schema_for!(PauseState), &out_dir);
+    export_schema(&schema_for!(FrozenAccountsResponse), &out_dir);
+    export_schema(&schema_for!(RateLimitResponse), &out_dir);
+    export_schema(&schema_for!(HistoryResponse), &out_dir);
+    export_schema(&schema_for!(ReservesResponse), &out_dir);
 }
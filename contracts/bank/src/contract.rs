@@ -1,22 +1,45 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, Uint128,
+    coins, from_binary, to_binary, Addr, BankMsg, Binary, CosmosMsg, Decimal, Deps, DepsMut, Empty,
+    Env, MessageInfo, Order, Querier, QuerierResult, Response, StdError, StdResult, Storage,
+    Timestamp, Uint128, WasmMsg,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use semver::Version;
+use std::ops::Deref;
 
 use crate::error::ContractError;
+use crate::ledger::{self, Book, Posting};
+use crate::migrations;
 use crate::msg::{
-    AccountResponse, BalanceResponse, ExecuteMsg, InstantiateMsg, QueryMsg, ReceiveMsg,
+    AccountInfo, AccountResponse, BalanceResponse, ConfigResponse, ExecuteMsg, FrozenAccount,
+    FrozenAccountsResponse, HistoryResponse, InstantiateMsg, JointAccountResponse,
+    ListAccountsResponse, ListProposalsResponse, MigrateMsg, OwnershipResponse, ProposalResponse,
+    QueryMsg, RateLimitResponse, ReceiveMsg, ReservesResponse, RoleGrant, RolesResponse,
+    TokenBalance, TokensResponse, TreasuryResponse,
 };
-use crate::state::{BalanceData, State, BALANCES, OWNER, STATE};
-use cw20::{Cw20Contract, Cw20ExecuteMsg, Cw20ReceiveMsg};
+use crate::state::{
+    accounts, AccountData, AccountKind, EntryKind, FeeConfig, FeeTier, Freeze, JointAccount,
+    JointAction, JournalEntry, Operation, PauseState, PendingOwner, Proposal, RateLimit, Role,
+    State, WindowUsage, ACCOUNT_FEE_TIERS, ACCOUNT_RATE_LIMITS, ACCOUNT_USAGE, ALLOWANCES,
+    BALANCES, FROZEN, GLOBAL_RATE_LIMITS, GLOBAL_USAGE, JOINT_ACCOUNTS, JOINT_USAGE, JOURNAL,
+    JOURNAL_SEQ, JOURNAL_START, NATIVE_TOKENS, OWNER_FEE_TIERS, PENDING_ACCOUNT_OWNERS,
+    PENDING_OWNER, PROPOSALS, PROPOSAL_COUNT, ROLES, STATE, TOKENS, TOTAL_BALANCES, TREASURY,
+};
+use cw20::{
+    AllAllowancesResponse, AllowanceInfo, AllowanceResponse, Cw20Contract, Cw20ExecuteMsg,
+    Cw20ReceiveMsg, Denom, Expiration,
+};
+use cw_storage_plus::Bound;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:bank";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Window in seconds over which a joint account's limit caps spends without approvals
+const JOINT_SPEND_WINDOW: u64 = 24 * 60 * 60;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -24,12 +47,18 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    let fee = msg.fee.unwrap_or_default();
+    validate_fee_config(&fee)?;
+
     let state = State {
-        owner: info.sender.clone(),
+        owner: Some(info.sender.clone()),
         currency: deps.api.addr_validate(msg.currency.as_str())?,
+        fee,
+        paused: PauseState::default(),
     };
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     STATE.save(deps.storage, &state)?;
+    TOKENS.save(deps.storage, &state.currency, &Empty {})?;
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
@@ -37,6 +66,35 @@ pub fn instantiate(
         .add_attribute("count", msg.currency.to_string()))
 }
 
+/// Upgrades the storage of a deployed bank from the version recorded by cw2
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: stored.contract,
+        });
+    }
+
+    let stored_version: Version = stored.version.parse()?;
+    let new_version: Version = CONTRACT_VERSION.parse()?;
+    if stored_version > new_version {
+        return Err(ContractError::CannotDowngrade {
+            stored: stored.version,
+            new: CONTRACT_VERSION.to_string(),
+        });
+    }
+
+    let applied = migrations::run(deps.storage, &stored_version)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", CONTRACT_VERSION)
+        .add_attribute("migrations", applied.join(",")))
+}
+
 /// ExecuteMsg is the message sent to the contract to execute.
 /// It is a union of all the possible messages that the contract can handle.
 /// The actual message is encoded in the data field.
@@ -44,52 +102,204 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::CreateAccount { account_name } => try_create(deps, info, account_name),
-        ExecuteMsg::Receive(msg) => try_receive(deps, info, msg),
-        ExecuteMsg::Withdraw { account, amount } => try_withdraw(deps, info, account, amount),
-        ExecuteMsg::Transfer { from, to, amount } => try_transfer(deps, info, from, to, amount),
+        ExecuteMsg::CreateAccount {
+            account_name,
+            lock_until,
+            penalty,
+        } => try_create(deps, env, info, account_name, lock_until, penalty),
+        ExecuteMsg::CreateJointAccount {
+            account_name,
+            signers,
+            threshold,
+            limit,
+            voting_period,
+        } => try_create_joint(
+            deps,
+            info,
+            account_name,
+            signers,
+            threshold,
+            limit,
+            voting_period,
+        ),
+        ExecuteMsg::Approve { proposal_id } => try_approve(deps, env, info, proposal_id),
+        ExecuteMsg::CloseProposal { proposal_id } => {
+            try_close_proposal(deps, env, info, proposal_id)
+        }
+        ExecuteMsg::Receive(msg) => try_receive(deps, env, info, msg),
+        ExecuteMsg::DepositNative { account } => try_deposit_native(deps, env, info, account),
+        ExecuteMsg::Withdraw {
+            account,
+            amount,
+            token,
+            recipient,
+        } => try_withdraw(deps, env, info, account, amount, token, recipient),
+        ExecuteMsg::WithdrawAndSend {
+            account,
+            amount,
+            token,
+            contract,
+            msg,
+        } => try_withdraw_and_send(deps, env, info, account, amount, token, contract, msg),
+        ExecuteMsg::Transfer {
+            from,
+            to,
+            amount,
+            token,
+        } => try_transfer(deps, env, info, from, to, amount, token),
+        ExecuteMsg::IncreaseAllowance {
+            account,
+            spender,
+            amount,
+            token,
+            expires,
+        } => try_increase_allowance(deps, env, info, account, spender, amount, token, expires),
+        ExecuteMsg::DecreaseAllowance {
+            account,
+            spender,
+            amount,
+            token,
+            expires,
+        } => try_decrease_allowance(deps, env, info, account, spender, amount, token, expires),
+        ExecuteMsg::TransferFrom {
+            from,
+            to,
+            amount,
+            token,
+        } => try_transfer_from(deps, env, info, from, to, amount, token),
+        ExecuteMsg::WithdrawFrom {
+            account,
+            amount,
+            token,
+            recipient,
+        } => try_withdraw_from(deps, env, info, account, amount, token, recipient),
+        ExecuteMsg::CloseAccount { account, sweep_to } => {
+            try_close_account(deps, env, info, account, sweep_to)
+        }
+        ExecuteMsg::TransferAccountOwnership { account, new_owner } => {
+            try_transfer_account_ownership(deps, info, account, new_owner)
+        }
+        ExecuteMsg::AcceptAccountOwnership { account } => {
+            try_accept_account_ownership(deps, info, account)
+        }
+        ExecuteMsg::CancelAccountOwnershipTransfer { account } => {
+            try_cancel_account_ownership_transfer(deps, info, account)
+        }
         ExecuteMsg::ChangeCurrency { currency } => try_change_currency(deps, info, currency),
+        ExecuteMsg::AddToken { token } => try_add_token(deps, info, token),
+        ExecuteMsg::AddNativeToken { denom } => try_add_native_token(deps, info, denom),
+        ExecuteMsg::RemoveToken { token } => try_remove_token(deps, info, token),
+        ExecuteMsg::WithdrawFees {
+            recipient,
+            amount,
+            token,
+        } => try_withdraw_fees(deps, info, recipient, amount, token),
+        ExecuteMsg::ReconcileSurplus { token } => try_reconcile_surplus(deps, env, info, token),
+        ExecuteMsg::RecoverTokens {
+            token,
+            amount,
+            recipient,
+        } => try_recover_tokens(deps, env, info, token, amount, recipient),
+        ExecuteMsg::UpdateFeeConfig { fee } => try_update_fee_config(deps, info, fee),
+        ExecuteMsg::SetAccountFeeTier { account, tier } => {
+            try_set_account_fee_tier(deps, info, account, tier)
+        }
+        ExecuteMsg::SetOwnerFeeTier { owner, tier } => {
+            try_set_owner_fee_tier(deps, info, owner, tier)
+        }
+        ExecuteMsg::ProposeOwner { new_owner, expiry } => {
+            try_propose_owner(deps, env, info, new_owner, expiry)
+        }
+        ExecuteMsg::AcceptOwnership {} => try_accept_ownership(deps, env, info),
+        ExecuteMsg::CancelOwnershipProposal {} => try_cancel_ownership_proposal(deps, info),
+        ExecuteMsg::RenounceOwnership {} => try_renounce_ownership(deps, info),
+        ExecuteMsg::Pause { operations } => try_set_paused(deps, info, operations, true),
+        ExecuteMsg::Unpause { operations } => try_set_paused(deps, info, operations, false),
+        ExecuteMsg::FreezeAccount {
+            account,
+            reason,
+            expires,
+        } => try_freeze_account(deps, env, info, account, reason, expires),
+        ExecuteMsg::UnfreezeAccount { account } => try_unfreeze_account(deps, info, account),
+        ExecuteMsg::SetAccountRateLimit {
+            account,
+            token,
+            limit,
+        } => try_set_account_rate_limit(deps, info, account, token, limit),
+        ExecuteMsg::SetGlobalRateLimit { token, limit } => {
+            try_set_global_rate_limit(deps, info, token, limit)
+        }
+        ExecuteMsg::GrantRole { role, address } => try_grant_role(deps, info, role, address),
+        ExecuteMsg::RevokeRole { role, address } => try_revoke_role(deps, info, role, address),
     }
 }
 
+/// Creates an account owned by the sender. With `lock_until` it is a savings
+/// account whose funds cannot leave before then, unless `penalty` allows
+/// breaking the lock early at a cost.
 pub fn try_create(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     account_name: String,
+    lock_until: Option<Timestamp>,
+    penalty: Option<Decimal>,
 ) -> Result<Response, ContractError> {
-    if BALANCES
-        .may_load(deps.storage, account_name.to_owned())?
-        .is_some()
-    {
-        return Err(ContractError::InvalidRequest(
-            "Account already exists".to_string(),
-        ));
+    ensure_not_paused(deps.storage, Operation::AccountCreation)?;
+
+    if accounts().may_load(deps.storage, &account_name)?.is_some() {
+        return Err(ContractError::AccountExists {
+            name: account_name.clone(),
+        });
     }
 
-    BALANCES.save(
-        deps.storage,
-        account_name.to_owned(),
-        &BalanceData {
-            address: info.sender.to_owned(),
-            value: Uint128::zero(),
-        },
-    )?;
+    let kind = match lock_until {
+        Some(lock_until) => {
+            if lock_until <= env.block.time {
+                return Err(ContractError::InvalidRequest(
+                    "Lock must end in the future".to_string(),
+                ));
+            }
+            // a full penalty would leave nothing to pay out
+            if matches!(penalty, Some(penalty) if penalty >= Decimal::one()) {
+                return Err(ContractError::InvalidRequest(
+                    "Penalty must be below 100%".to_string(),
+                ));
+            }
+            if matches!(penalty, Some(penalty) if penalty.is_zero()) {
+                return Err(ContractError::InvalidRequest(
+                    "Penalty must not be zero".to_string(),
+                ));
+            }
+            AccountKind::TimeLocked {
+                lock_until,
+                penalty,
+            }
+        }
+        None => {
+            if penalty.is_some() {
+                return Err(ContractError::InvalidRequest(
+                    "Penalty requires a lock".to_string(),
+                ));
+            }
+            AccountKind::Standard
+        }
+    };
 
-    OWNER.update(
+    accounts().save(
         deps.storage,
-        &info.sender,
-        |state| -> Result<Vec<String>, ContractError> {
-            match state {
-                None => Ok(vec![account_name.clone()]),
-                Some(state) => Ok([state.clone(), vec![account_name.clone()]].concat()),
-            }
+        &account_name,
+        &AccountData {
+            address: info.sender.to_owned(),
+            kind,
         },
     )?;
+    open_journal(deps.storage, &account_name)?;
 
     Ok(Response::new()
         .add_attribute("method", "create")
@@ -99,490 +309,5912 @@ pub fn try_create(
 
 pub fn try_receive(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     wrapped: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
     // cw20 address authentication
-    let config = STATE.load(deps.storage)?;
-    if config.currency != info.sender {
+    if !TOKENS.has(deps.storage, &info.sender) {
         return Err(ContractError::Unauthorized {});
     }
 
     let msg: ReceiveMsg = from_binary(&wrapped.msg)?;
     match msg {
-        ReceiveMsg::Deposit { account } => {
-            receive_deposit(deps, account, wrapped.amount, info.sender)
-        }
+        ReceiveMsg::Deposit { account } => receive_deposit(
+            deps,
+            env,
+            account,
+            wrapped.amount,
+            info.sender,
+            wrapped.sender,
+        ),
     }
 }
 
 pub fn receive_deposit(
     deps: DepsMut,
+    env: Env,
     account: String,
     amount: Uint128,
     sender: Addr,
+    depositor: String,
 ) -> Result<Response, ContractError> {
-    BALANCES.update(
+    ensure_not_paused(deps.storage, Operation::Deposits)?;
+
+    if accounts().may_load(deps.storage, &account)?.is_none() {
+        return Err(ContractError::AccountNotFound {
+            name: account.clone(),
+        });
+    }
+
+    credit_balance(
         deps.storage,
-        account.to_owned(),
-        |balance| -> Result<BalanceData, ContractError> {
-            match balance {
-                None => Err(ContractError::InvalidRequest(
-                    "Account does not exist".to_string(),
-                )),
-                Some(balance) => Ok(BalanceData {
-                    address: balance.address.to_owned(),
-                    value: balance.value + amount,
-                }),
-            }
-        },
+        &env,
+        &account,
+        sender.as_str(),
+        amount,
+        &depositor,
     )?;
     Ok(Response::new()
         .add_attribute("method", "deposit")
-        .add_attribute("owner", sender)
+        .add_attribute("owner", sender.to_owned())
         .add_attribute("address", account)
+        .add_attribute("token", sender)
         .add_attribute("amount", amount.to_string()))
 }
 
-pub fn try_withdraw(
+/// Credits the native coins sent along with the message to `account`
+pub fn try_deposit_native(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     account: String,
-    amount: Uint128,
 ) -> Result<Response, ContractError> {
-    let balance = BALANCES.may_load(deps.storage, account.to_owned())?;
+    ensure_not_paused(deps.storage, Operation::Deposits)?;
 
-    if balance.is_none() {
-        return Err(ContractError::InvalidRequest(
-            "Account does not exist".to_string(),
-        ));
+    if accounts().may_load(deps.storage, &account)?.is_none() {
+        return Err(ContractError::AccountNotFound {
+            name: account.clone(),
+        });
+    }
+
+    if info.funds.is_empty() {
+        return Err(ContractError::ZeroAmount {});
     }
 
-    let balance = balance.unwrap();
+    let mut res = Response::new()
+        .add_attribute("method", "deposit_native")
+        .add_attribute("owner", info.sender.to_owned())
+        .add_attribute("address", account.to_owned());
 
-    if balance.address != info.sender {
-        return Err(ContractError::Unauthorized {});
+    for coin in info.funds {
+        if !NATIVE_TOKENS.has(deps.storage, &coin.denom) {
+            return Err(ContractError::UnsupportedToken { token: coin.denom });
+        }
+        credit_balance(
+            deps.storage,
+            &env,
+            &account,
+            &coin.denom,
+            coin.amount,
+            info.sender.as_str(),
+        )?;
+        res = res
+            .add_attribute("token", coin.denom)
+            .add_attribute("amount", coin.amount.to_string());
     }
 
-    if balance.value < amount {
-        return Err(ContractError::InvalidRequest(
-            "Insufficient balance".to_string(),
-        ));
+    Ok(res)
+}
+
+pub fn try_withdraw(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    account: String,
+    amount: Uint128,
+    token: Option<String>,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.storage, Operation::Withdrawals)?;
+    ensure_not_frozen(deps.storage, &env, &account)?;
+
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender.to_owned(),
+    };
+
+    if needs_approval(deps.as_ref(), &env, &info.sender, &account, amount)? {
+        let token = resolve_token(deps.as_ref(), token)?;
+        let action = JointAction::Withdraw {
+            amount,
+            token,
+            recipient,
+        };
+        return propose(deps, env, info, account, action);
     }
 
-    BALANCES.save(
-        deps.storage,
-        account.to_owned(),
-        &BalanceData {
-            address: balance.address,
-            value: balance.value - amount,
-        },
+    let (token, penalty) = debit_withdrawal(
+        deps.branch(),
+        &env,
+        &info,
+        (&account, amount, token),
+        &recipient,
     )?;
 
     let mut res = Response::new()
         .add_attribute("method", "withdraw")
         .add_attribute("owner", info.sender.to_owned())
         .add_attribute("address", account)
-        .add_attribute("amount", amount.to_string());
+        .add_attribute("token", token.to_owned())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("recipient", recipient.to_owned())
+        .add_attribute("penalty", penalty.to_string());
 
-    let currency = STATE.load(deps.storage)?.currency;
-    let cw20 = Cw20Contract(currency);
-    let msg = cw20.call(Cw20ExecuteMsg::Transfer {
-        recipient: info.sender.to_string(),
-        amount: amount,
-    })?;
+    let msg = payout(
+        deps.as_ref(),
+        &token,
+        &recipient,
+        amount.checked_sub(penalty)?,
+    )?;
 
     res = res.add_message(msg);
 
     Ok(res)
 }
 
-pub fn try_transfer(
-    deps: DepsMut,
+/// Withdraws funds straight into `contract`, executing `msg` on it: cw20 tokens
+/// go through `Cw20ExecuteMsg::Send`, native coins are attached to the call
+#[allow(clippy::too_many_arguments)]
+pub fn try_withdraw_and_send(
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    from: String,
-    to: String,
+    account: String,
     amount: Uint128,
+    token: Option<String>,
+    contract: String,
+    msg: Binary,
 ) -> Result<Response, ContractError> {
-    let balance = BALANCES.may_load(deps.storage, from.to_owned())?;
+    ensure_not_paused(deps.storage, Operation::Withdrawals)?;
+    ensure_not_frozen(deps.storage, &env, &account)?;
 
-    if balance.is_none() {
-        return Err(ContractError::InvalidRequest(
-            "Account from does not exist".to_string(),
-        ));
+    let contract = deps.api.addr_validate(&contract)?;
+
+    if needs_approval(deps.as_ref(), &env, &info.sender, &account, amount)? {
+        let token = resolve_token(deps.as_ref(), token)?;
+        let action = JointAction::WithdrawAndSend {
+            amount,
+            token,
+            contract,
+            msg,
+        };
+        return propose(deps, env, info, account, action);
     }
 
-    let balance_from = balance.unwrap();
+    let (token, penalty) = debit_withdrawal(
+        deps.branch(),
+        &env,
+        &info,
+        (&account, amount, token),
+        &contract,
+    )?;
+    let msg = send_to_contract(
+        deps.as_ref(),
+        &token,
+        &contract,
+        amount.checked_sub(penalty)?,
+        msg,
+    )?;
 
-    if balance_from.address != info.sender {
-        return Err(ContractError::Unauthorized {});
-    }
+    Ok(Response::new()
+        .add_message(msg)
+        .add_attribute("method", "withdraw_and_send")
+        .add_attribute("owner", info.sender)
+        .add_attribute("address", account)
+        .add_attribute("token", token)
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("contract", contract)
+        .add_attribute("penalty", penalty.to_string()))
+}
 
-    if balance_from.value < amount {
-        return Err(ContractError::InvalidRequest(
-            "Insufficient balance".to_string(),
-        ));
+/// Builds the message paying `amount` of `token` into `contract` and executing `msg` on it
+fn send_to_contract(
+    deps: Deps,
+    token: &str,
+    contract: &Addr,
+    amount: Uint128,
+    msg: Binary,
+) -> StdResult<CosmosMsg> {
+    if NATIVE_TOKENS.has(deps.storage, token) {
+        Ok(WasmMsg::Execute {
+            contract_addr: contract.to_string(),
+            msg,
+            funds: coins(amount.u128(), token),
+        }
+        .into())
+    } else {
+        Cw20Contract(Addr::unchecked(token)).call(Cw20ExecuteMsg::Send {
+            contract: contract.to_string(),
+            amount,
+            msg,
+        })
     }
+}
 
-    let balance = BALANCES.may_load(deps.storage, to.to_owned())?;
+/// Takes `amount` out of an account of the sender, returning the resolved token
+/// and the early break penalty kept from the amount
+fn debit_withdrawal(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    (account, amount, token): (&str, Uint128, Option<String>),
+    recipient: &Addr,
+) -> Result<(String, Uint128), ContractError> {
+    authorize_spend(deps.storage, env, &info.sender, account, amount)?;
 
-    if balance.is_none() {
-        return Err(ContractError::InvalidRequest(
-            "Account to does not exist".to_string(),
-        ));
-    }
+    let token = resolve_token(deps.as_ref(), token)?;
+    let penalty = debit_balance(deps.storage, env, account, &token, amount, recipient)?;
 
-    let balance_to = balance.unwrap();
+    Ok((token, penalty))
+}
 
-    let fee = if balance_from.address == balance_to.address {
-        Uint128::zero()
-    } else {
-        amount
-            .checked_div(Uint128::new(100))
-            .unwrap_or(Uint128::zero())
-    };
+/// Takes `amount` of `token` out of an account and out of the bank's liabilities,
+/// counting it against the withdrawal limits. Returns the early break penalty
+/// moved to the treasury, which the caller keeps from the payout.
+fn debit_balance(
+    storage: &mut dyn Storage,
+    env: &Env,
+    account: &str,
+    token: &str,
+    amount: Uint128,
+    recipient: &Addr,
+) -> Result<Uint128, ContractError> {
+    let data = accounts().load(storage, account)?;
+    let penalty = early_break_penalty(&data, env, amount)?;
+    consume_rate_limits(storage, env, account, token, amount, true)?;
 
-    BALANCES.save(
-        deps.storage,
-        from.to_owned(),
-        &BalanceData {
-            address: balance_from.address,
-            value: balance_from.value - amount,
-        },
+    ledger::post(
+        storage,
+        token,
+        &[
+            Posting::Debit(Book::Account(account), amount),
+            Posting::Credit(Book::Treasury, penalty),
+            Posting::Credit(Book::External, amount.checked_sub(penalty)?),
+        ],
+    )?;
+    journal(
+        storage,
+        env,
+        account,
+        EntryKind::Withdrawal,
+        recipient.as_str(),
+        (token, amount, penalty),
     )?;
 
-    BALANCES.save(
-        deps.storage,
-        to.to_owned(),
-        &BalanceData {
-            address: balance_to.address,
-            value: balance_to.value + amount - fee,
+    Ok(penalty)
+}
+
+/// What leaving a time-locked account costs before the lock ends, failing if
+/// the lock cannot be broken
+fn early_break_penalty(
+    data: &AccountData,
+    env: &Env,
+    amount: Uint128,
+) -> Result<Uint128, ContractError> {
+    match &data.kind {
+        AccountKind::TimeLocked {
+            lock_until,
+            penalty,
+        } if env.block.time < *lock_until => match penalty {
+            Some(penalty) => Ok(amount * *penalty),
+            None => Err(ContractError::AccountLocked { until: *lock_until }),
         },
+        _ => Ok(Uint128::zero()),
+    }
+}
+
+/// Pays out of an account on behalf of its owner, consuming the sender's allowance
+pub fn try_withdraw_from(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    account: String,
+    amount: Uint128,
+    token: Option<String>,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.storage, Operation::Withdrawals)?;
+    ensure_not_frozen(deps.storage, &env, &account)?;
+
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender.to_owned(),
+    };
+
+    if accounts().may_load(deps.storage, &account)?.is_none() {
+        return Err(ContractError::AccountNotFound {
+            name: account.clone(),
+        });
+    }
+
+    let token = resolve_token(deps.as_ref(), token)?;
+    deduct_allowance(deps.storage, &env, &account, &token, &info.sender, amount)?;
+    let penalty = debit_balance(deps.storage, &env, &account, &token, amount, &recipient)?;
+
+    let msg = payout(
+        deps.as_ref(),
+        &token,
+        &recipient,
+        amount.checked_sub(penalty)?,
     )?;
 
+    Ok(Response::new()
+        .add_message(msg)
+        .add_attribute("method", "withdraw_from")
+        .add_attribute("spender", info.sender)
+        .add_attribute("address", account)
+        .add_attribute("token", token)
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("recipient", recipient)
+        .add_attribute("penalty", penalty.to_string()))
+}
+
+pub fn try_transfer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    from: String,
+    to: String,
+    amount: Uint128,
+    token: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.storage, Operation::Transfers)?;
+    ensure_not_frozen(deps.storage, &env, &from)?;
+
+    if needs_approval(deps.as_ref(), &env, &info.sender, &from, amount)? {
+        if accounts().may_load(deps.storage, &to)?.is_none() {
+            return Err(ContractError::AccountNotFound { name: to });
+        }
+        let token = resolve_token(deps.as_ref(), token)?;
+        let action = JointAction::Transfer { to, amount, token };
+        return propose(deps, env, info, from, action);
+    }
+
+    let data_from = authorize_spend(deps.storage, &env, &info.sender, &from, amount)?;
+
+    let token = resolve_token(deps.as_ref(), token)?;
+    let (fee, tier, penalty) =
+        transfer_funds(deps, &env, (&from, &data_from), &to, &token, amount)?;
+
     Ok(Response::new()
         .add_attribute("method", "transfer")
         .add_attribute("owner", info.sender)
         .add_attribute("from", from)
         .add_attribute("to", to)
         .add_attribute("amount", amount.to_string())
-        .add_attribute("fee", fee.to_string()))
+        .add_attribute("fee", fee.to_string())
+        .add_attribute("fee_tier", tier)
+        .add_attribute("token", token)
+        .add_attribute("penalty", penalty.to_string()))
 }
 
-pub fn try_change_currency(
+/// Transfers out of an account on behalf of its owner, consuming the sender's allowance.
+/// The fee is charged as if the owner had made the transfer.
+pub fn try_transfer_from(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    currency: String,
+    from: String,
+    to: String,
+    amount: Uint128,
+    token: Option<String>,
 ) -> Result<Response, ContractError> {
-    let config = STATE.load(deps.storage)?;
-    if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    ensure_not_paused(deps.storage, Operation::Transfers)?;
+    ensure_not_frozen(deps.storage, &env, &from)?;
+
+    let data = accounts().may_load(deps.storage, &from)?;
+
+    if data.is_none() {
+        return Err(ContractError::AccountNotFound { name: from.clone() });
     }
-    STATE.save(
-        deps.storage,
-        &State {
-            owner: config.owner,
-            currency: deps.api.addr_validate(&currency)?,
-        },
-    )?;
+
+    let data_from = data.unwrap();
+
+    let token = resolve_token(deps.as_ref(), token)?;
+    deduct_allowance(deps.storage, &env, &from, &token, &info.sender, amount)?;
+
+    let (fee, tier, penalty) =
+        transfer_funds(deps, &env, (&from, &data_from), &to, &token, amount)?;
+
     Ok(Response::new()
-        .add_attribute("method", "change_currency")
-        .add_attribute("owner", info.sender)
-        .add_attribute("currency", currency))
+        .add_attribute("method", "transfer_from")
+        .add_attribute("spender", info.sender)
+        .add_attribute("from", from)
+        .add_attribute("to", to)
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("fee", fee.to_string())
+        .add_attribute("fee_tier", tier)
+        .add_attribute("token", token)
+        .add_attribute("penalty", penalty.to_string()))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetAccount { address } => to_binary(&query_account(deps, address)?),
-        QueryMsg::GetBalance { account } => to_binary(&query_balance(deps, account)?),
-    }
-}
+/// Moves `amount` of `token` from an account into the account named `to`
+fn transfer_funds(
+    deps: DepsMut,
+    env: &Env,
+    (from, data_from): (&str, &AccountData),
+    to: &str,
+    token: &str,
+    amount: Uint128,
+) -> Result<(Uint128, String, Uint128), ContractError> {
+    let data = accounts().may_load(deps.storage, to)?;
 
-pub fn query_account(deps: Deps, address: String) -> StdResult<AccountResponse> {
-    let addr = deps.api.addr_validate(address.as_str())?;
-    let owner = OWNER.may_load(deps.storage, &addr)?;
-    if let Some(account) = owner {
-        Ok(AccountResponse { account })
-    } else {
-        Err(StdError::NotFound {
-            kind: "account".to_string(),
-        })
+    if data.is_none() {
+        return Err(ContractError::AccountNotFound {
+            name: to.to_string(),
+        });
     }
+
+    let data_to = data.unwrap();
+
+    move_funds(deps, env, (from, data_from), (to, &data_to), token, amount)
 }
 
-pub fn query_balance(deps: Deps, account: String) -> StdResult<BalanceResponse> {
-    let balance = BALANCES.may_load(deps.storage, account.to_owned())?;
-    if let Some(balance) = balance {
-        Ok(BalanceResponse {
-            balance: balance.value,
-        })
+/// Moves `amount` of `token` between two accounts, charging the transfer fee
+/// unless both are single owner accounts of the same owner, plus the early break penalty of a
+/// time-locked account. Returns the fee, its tier and the penalty.
+/// Transfers to another owner count against the account's withdrawal limit.
+fn move_funds(
+    deps: DepsMut,
+    env: &Env,
+    (from, data_from): (&str, &AccountData),
+    (to, data_to): (&str, &AccountData),
+    token: &str,
+    amount: Uint128,
+) -> Result<(Uint128, String, Uint128), ContractError> {
+    let penalty = early_break_penalty(data_from, env, amount)?;
+    let same_owner = data_from.address == data_to.address
+        && !JOINT_ACCOUNTS.has(deps.storage, from)
+        && !JOINT_ACCOUNTS.has(deps.storage, to);
+    let (fee, tier) = if same_owner {
+        (Uint128::zero(), "same_owner".to_string())
     } else {
-        Err(StdError::NotFound {
-            kind: "balance".to_string(),
-        })
+        consume_rate_limits(deps.storage, env, from, token, amount, false)?;
+        resolve_fee(deps.as_ref(), from, data_from, to, data_to, amount)?
+    };
+
+    let charged = fee.checked_add(penalty)?;
+    if charged > amount {
+        return Err(ContractError::ChargesExceedAmount { amount, charged });
     }
+    let received = amount.checked_sub(charged)?;
+    ledger::post(
+        deps.storage,
+        token,
+        &[
+            Posting::Debit(Book::Account(from), amount),
+            Posting::Credit(Book::Account(to), received),
+            Posting::Credit(Book::Treasury, charged),
+        ],
+    )?;
+
+    journal(
+        deps.storage,
+        env,
+        from,
+        EntryKind::TransferOut,
+        to,
+        (token, amount, charged),
+    )?;
+    journal(
+        deps.storage,
+        env,
+        to,
+        EntryKind::TransferIn,
+        from,
+        (token, received, Uint128::zero()),
+    )?;
+
+    Ok((fee, tier, penalty))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::from_binary;
-    use cosmwasm_std::testing::{
-        mock_dependencies, mock_env, mock_info, MockQuerier, MOCK_CONTRACT_ADDR,
-    };
-    use cw20::Cw20QueryMsg;
+/// Deletes an account, paying everything it holds out to the owner or moving
+/// it into `sweep_to` under the usual fee rules, so the name can be taken again
+pub fn try_close_account(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    account: String,
+    sweep_to: Option<String>,
+) -> Result<Response, ContractError> {
+    let data = load_owned_account(deps.as_ref(), &info.sender, &account)?;
 
-    #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies();
+    let operation = match sweep_to {
+        Some(_) => Operation::Transfers,
+        None => Operation::Withdrawals,
+    };
+    ensure_not_paused(deps.storage, operation)?;
+    ensure_not_frozen(deps.storage, &env, &account)?;
 
-        let msg = InstantiateMsg {
-            currency: String::from(MOCK_CONTRACT_ADDR),
-        };
+    let sweep_to = match sweep_to {
+        Some(to) => {
+            if to == account {
+                return Err(ContractError::InvalidRequest(
+                    "Cannot sweep an account into itself".to_string(),
+                ));
+            }
+            let data_to = accounts().may_load(deps.storage, &to)?;
+            if data_to.is_none() {
+                return Err(ContractError::AccountNotFound { name: to.clone() });
+            }
+            Some((to, data_to.unwrap()))
+        }
+        None => None,
+    };
 
-        let info = mock_info("creator", &[]);
+    let mut res = Response::new()
+        .add_attribute("method", "close_account")
+        .add_attribute("owner", info.sender.to_owned())
+        .add_attribute("address", account.to_owned());
 
-        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-        assert_eq!(0, res.messages.len());
+    for holding in load_holdings(deps.storage, &account)? {
+        res = res
+            .add_attribute("token", holding.token.to_owned())
+            .add_attribute("amount", holding.amount.to_string());
+        match &sweep_to {
+            Some((to, data_to)) => {
+                let (fee, _, penalty) = move_funds(
+                    deps.branch(),
+                    &env,
+                    (&account, &data),
+                    (to, data_to),
+                    &holding.token,
+                    holding.amount,
+                )?;
+                res = res
+                    .add_attribute("fee", fee.to_string())
+                    .add_attribute("penalty", penalty.to_string());
+            }
+            None => {
+                let penalty = debit_balance(
+                    deps.storage,
+                    &env,
+                    &account,
+                    &holding.token,
+                    holding.amount,
+                    &info.sender,
+                )?;
+                let paid = holding.amount.checked_sub(penalty)?;
+                let msg = payout(deps.as_ref(), &holding.token, &info.sender, paid)?;
+                res = res
+                    .add_message(msg)
+                    .add_attribute("penalty", penalty.to_string());
+            }
+        }
     }
 
-    #[test]
-    fn proper_add_account_and_deposit() {
-        let mut deps = mock_dependencies();
+    let tokens = BALANCES
+        .prefix(&account)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for token in tokens {
+        BALANCES.remove(deps.storage, (&account, &token));
+    }
+    ACCOUNT_FEE_TIERS.remove(deps.storage, &account);
+    PENDING_ACCOUNT_OWNERS.remove(deps.storage, &account);
+    clear_allowances(deps.storage, &account)?;
+    clear_rate_limits(deps.storage, &account)?;
+    accounts().remove(deps.storage, &account)?;
 
-        let msg = InstantiateMsg {
-            currency: String::from(MOCK_CONTRACT_ADDR),
-        };
+    if let Some((to, _)) = sweep_to {
+        res = res.add_attribute("sweep_to", to);
+    }
 
-        let creator = mock_info("creator", &[]);
+    Ok(res)
+}
 
-        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
-        assert_eq!(0, res.messages.len());
+/// Offers an account to `new_owner`, who takes it over with `AcceptAccountOwnership`
+pub fn try_transfer_account_ownership(
+    deps: DepsMut,
+    info: MessageInfo,
+    account: String,
+    new_owner: String,
+) -> Result<Response, ContractError> {
+    let data = load_owned_account(deps.as_ref(), &info.sender, &account)?;
+
+    let new_owner = deps.api.addr_validate(&new_owner)?;
+    if new_owner == data.address {
+        return Err(ContractError::InvalidRequest(
+            "Account is already owned by the new owner".to_string(),
+        ));
+    }
+
+    PENDING_ACCOUNT_OWNERS.save(deps.storage, &account, &new_owner)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "transfer_account_ownership")
+        .add_attribute("owner", info.sender)
+        .add_attribute("address", account)
+        .add_attribute("new_owner", new_owner))
+}
+
+/// Moves an offered account to the sender, the owner index follows the
+/// account data in the same write
+pub fn try_accept_account_ownership(
+    deps: DepsMut,
+    info: MessageInfo,
+    account: String,
+) -> Result<Response, ContractError> {
+    let pending = PENDING_ACCOUNT_OWNERS.may_load(deps.storage, &account)?;
+
+    if pending.is_none() {
+        return Err(ContractError::NoPendingOwner {});
+    }
+
+    if pending.unwrap() != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let previous = accounts().load(deps.storage, &account)?;
+    accounts().replace(
+        deps.storage,
+        &account,
+        Some(&AccountData {
+            address: info.sender.to_owned(),
+            ..previous.clone()
+        }),
+        Some(&previous),
+    )?;
+    PENDING_ACCOUNT_OWNERS.remove(deps.storage, &account);
+    // spenders were trusted by the previous owner, not the new one
+    clear_allowances(deps.storage, &account)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "accept_account_ownership")
+        .add_attribute("previous_owner", previous.address)
+        .add_attribute("owner", info.sender)
+        .add_attribute("address", account))
+}
+
+pub fn try_cancel_account_ownership_transfer(
+    deps: DepsMut,
+    info: MessageInfo,
+    account: String,
+) -> Result<Response, ContractError> {
+    load_owned_account(deps.as_ref(), &info.sender, &account)?;
+
+    if PENDING_ACCOUNT_OWNERS
+        .may_load(deps.storage, &account)?
+        .is_none()
+    {
+        return Err(ContractError::NoPendingOwner {});
+    }
+    PENDING_ACCOUNT_OWNERS.remove(deps.storage, &account);
+
+    Ok(Response::new()
+        .add_attribute("method", "cancel_account_ownership_transfer")
+        .add_attribute("owner", info.sender)
+        .add_attribute("address", account))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn try_increase_allowance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    account: String,
+    spender: String,
+    amount: Uint128,
+    token: Option<String>,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    load_owned_account(deps.as_ref(), &info.sender, &account)?;
+
+    let spender = deps.api.addr_validate(&spender)?;
+    if spender == info.sender {
+        return Err(ContractError::CannotSetOwnAccount {});
+    }
+
+    let token = resolve_token(deps.as_ref(), token)?;
+    if let Some(expires) = expires {
+        if expires.is_expired(&env.block) {
+            return Err(ContractError::Expired {});
+        }
+    }
+
+    ALLOWANCES.update(
+        deps.storage,
+        (&account, &token, &spender),
+        |allow| -> StdResult<_> {
+            let mut allow = allow.unwrap_or_default();
+            if let Some(expires) = expires {
+                allow.expires = expires;
+            }
+            allow.allowance = allow.allowance.checked_add(amount)?;
+            Ok(allow)
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "increase_allowance")
+        .add_attribute("owner", info.sender)
+        .add_attribute("address", account)
+        .add_attribute("spender", spender)
+        .add_attribute("token", token)
+        .add_attribute("amount", amount.to_string()))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn try_decrease_allowance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    account: String,
+    spender: String,
+    amount: Uint128,
+    token: Option<String>,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    load_owned_account(deps.as_ref(), &info.sender, &account)?;
+
+    let spender = deps.api.addr_validate(&spender)?;
+    if spender == info.sender {
+        return Err(ContractError::CannotSetOwnAccount {});
+    }
+
+    let token = resolve_token(deps.as_ref(), token)?;
+    let key = (account.as_str(), token.as_str(), &spender);
+    let mut allowance = ALLOWANCES
+        .may_load(deps.storage, key)?
+        .ok_or(ContractError::NoAllowance {})?;
+
+    if amount < allowance.allowance {
+        if let Some(expires) = expires {
+            if expires.is_expired(&env.block) {
+                return Err(ContractError::Expired {});
+            }
+            allowance.expires = expires;
+        }
+        allowance.allowance = allowance.allowance.checked_sub(amount)?;
+        ALLOWANCES.save(deps.storage, key, &allowance)?;
+    } else {
+        ALLOWANCES.remove(deps.storage, key);
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "decrease_allowance")
+        .add_attribute("owner", info.sender)
+        .add_attribute("address", account)
+        .add_attribute("spender", spender)
+        .add_attribute("token", token)
+        .add_attribute("amount", amount.to_string()))
+}
+
+/// Lowers the allowance of `spender` on an account by `amount`, failing if it
+/// has expired or does not cover the amount
+fn deduct_allowance(
+    storage: &mut dyn Storage,
+    env: &Env,
+    account: &str,
+    token: &str,
+    spender: &Addr,
+    amount: Uint128,
+) -> Result<AllowanceResponse, ContractError> {
+    ALLOWANCES.update(storage, (account, token, spender), |allow| {
+        let mut allow = allow.ok_or(ContractError::NoAllowance {})?;
+        if allow.expires.is_expired(&env.block) {
+            return Err(ContractError::Expired {});
+        }
+        if allow.allowance < amount {
+            return Err(ContractError::InsufficientAllowance {
+                allowance: allow.allowance,
+                required: amount,
+            });
+        }
+        allow.allowance = allow.allowance.checked_sub(amount)?;
+        Ok(allow)
+    })
+}
+
+fn clear_allowances(storage: &mut dyn Storage, account: &str) -> StdResult<()> {
+    let keys = ALLOWANCES
+        .sub_prefix(account)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (token, spender) in keys {
+        ALLOWANCES.remove(storage, (account, &token, &spender));
+    }
+    Ok(())
+}
+
+/// Creates an account controlled by several signers, the sender among them.
+/// Withdrawals and transfers taking more than `limit` out of it within a day
+/// only go through once `threshold` signers approved them within
+/// `voting_period` seconds.
+pub fn try_create_joint(
+    deps: DepsMut,
+    info: MessageInfo,
+    account_name: String,
+    signers: Vec<String>,
+    threshold: u32,
+    limit: Uint128,
+    voting_period: u64,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.storage, Operation::AccountCreation)?;
+
+    if accounts().may_load(deps.storage, &account_name)?.is_some() {
+        return Err(ContractError::AccountExists {
+            name: account_name.clone(),
+        });
+    }
+
+    let mut addrs: Vec<Addr> = vec![];
+    for signer in signers {
+        let signer = deps.api.addr_validate(&signer)?;
+        if addrs.contains(&signer) {
+            return Err(ContractError::InvalidRequest(
+                "Duplicate signer".to_string(),
+            ));
+        }
+        addrs.push(signer);
+    }
+
+    if !addrs.contains(&info.sender) {
+        return Err(ContractError::InvalidRequest(
+            "Sender must be a signer".to_string(),
+        ));
+    }
+
+    if threshold == 0 || threshold as usize > addrs.len() {
+        return Err(ContractError::InvalidThreshold {
+            threshold,
+            signers: addrs.len() as u32,
+        });
+    }
+
+    if voting_period == 0 {
+        return Err(ContractError::InvalidRequest(
+            "Voting period must not be zero".to_string(),
+        ));
+    }
+
+    accounts().save(
+        deps.storage,
+        &account_name,
+        &AccountData {
+            address: info.sender.to_owned(),
+            kind: AccountKind::Standard,
+        },
+    )?;
+    open_journal(deps.storage, &account_name)?;
+    JOINT_ACCOUNTS.save(
+        deps.storage,
+        &account_name,
+        &JointAccount {
+            signers: addrs,
+            threshold,
+            limit,
+            voting_period,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "create_joint")
+        .add_attribute("owner", info.sender)
+        .add_attribute("address", account_name)
+        .add_attribute("threshold", threshold.to_string())
+        .add_attribute("limit", limit.to_string()))
+}
+
+fn ensure_signer(joint: &JointAccount, sender: &Addr) -> Result<(), ContractError> {
+    if !joint.signers.contains(sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+/// Whether spending `amount` out of an account has to be approved by its signers
+/// first, as it would take the account over its limit in the current window
+fn needs_approval(
+    deps: Deps,
+    env: &Env,
+    sender: &Addr,
+    account: &str,
+    amount: Uint128,
+) -> Result<bool, ContractError> {
+    match JOINT_ACCOUNTS.may_load(deps.storage, account)? {
+        Some(joint) => {
+            ensure_signer(&joint, sender)?;
+            let usage = JOINT_USAGE.may_load(deps.storage, account)?;
+            let usage = current_window(&joint_spend_limit(&joint), usage, env);
+            Ok(amount > joint.limit.saturating_sub(usage.spent))
+        }
+        None => Ok(false),
+    }
+}
+
+fn joint_spend_limit(joint: &JointAccount) -> RateLimit {
+    RateLimit {
+        amount: joint.limit,
+        window: JOINT_SPEND_WINDOW,
+    }
+}
+
+/// Records `action` as a proposal approved by its proposer, open for the
+/// voting period of the account
+fn propose(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    account: String,
+    action: JointAction,
+) -> Result<Response, ContractError> {
+    let joint = JOINT_ACCOUNTS.load(deps.storage, &account)?;
+    let id = PROPOSAL_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    PROPOSAL_COUNT.save(deps.storage, &id)?;
+
+    let proposal = Proposal {
+        account,
+        action,
+        approvals: vec![],
+        expires: Expiration::AtTime(env.block.time.plus_seconds(joint.voting_period)),
+    };
+    let res = Response::new()
+        .add_attribute("method", "propose")
+        .add_attribute("address", proposal.account.to_owned());
+
+    approve(deps, env, res, info.sender, id, proposal)
+}
+
+pub fn try_approve(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    proposal_id: u64,
+) -> Result<Response, ContractError> {
+    let proposal = PROPOSALS.load(deps.storage, proposal_id)?;
+
+    let res = Response::new()
+        .add_attribute("method", "approve")
+        .add_attribute("address", proposal.account.to_owned());
+
+    approve(deps, env, res, info.sender, proposal_id, proposal)
+}
+
+/// Drops a proposal that is not going to be executed. Signers of the account
+/// may close it at any time, anyone else once it expired.
+pub fn try_close_proposal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    proposal_id: u64,
+) -> Result<Response, ContractError> {
+    let proposal = PROPOSALS.load(deps.storage, proposal_id)?;
+
+    if !proposal.expires.is_expired(&env.block) {
+        let joint = JOINT_ACCOUNTS.load(deps.storage, &proposal.account)?;
+        ensure_signer(&joint, &info.sender)?;
+    }
+
+    PROPOSALS.remove(deps.storage, proposal_id);
+
+    Ok(Response::new()
+        .add_attribute("method", "close_proposal")
+        .add_attribute("sender", info.sender)
+        .add_attribute("address", proposal.account)
+        .add_attribute("proposal_id", proposal_id.to_string()))
+}
+
+/// Adds the approval of `signer`, executing the proposal once the threshold is met
+fn approve(
+    deps: DepsMut,
+    env: Env,
+    res: Response,
+    signer: Addr,
+    id: u64,
+    mut proposal: Proposal,
+) -> Result<Response, ContractError> {
+    let joint = JOINT_ACCOUNTS.load(deps.storage, &proposal.account)?;
+    ensure_signer(&joint, &signer)?;
+
+    if proposal.expires.is_expired(&env.block) {
+        return Err(ContractError::ProposalExpired {});
+    }
+    if proposal.approvals.contains(&signer) {
+        return Err(ContractError::AlreadyApproved {});
+    }
+    proposal.approvals.push(signer.to_owned());
+
+    let mut res = res
+        .add_attribute("signer", signer)
+        .add_attribute("proposal_id", id.to_string())
+        .add_attribute("approvals", proposal.approvals.len().to_string());
+
+    if (proposal.approvals.len() as u32) < joint.threshold {
+        PROPOSALS.save(deps.storage, id, &proposal)?;
+        return Ok(res);
+    }
+
+    PROPOSALS.remove(deps.storage, id);
+    let account = proposal.account;
+    match proposal.action {
+        JointAction::Withdraw {
+            amount,
+            token,
+            recipient,
+        } => {
+            ensure_not_paused(deps.storage, Operation::Withdrawals)?;
+            ensure_not_frozen(deps.storage, &env, &account)?;
+            let penalty = debit_balance(deps.storage, &env, &account, &token, amount, &recipient)?;
+            let msg = payout(
+                deps.as_ref(),
+                &token,
+                &recipient,
+                amount.checked_sub(penalty)?,
+            )?;
+            res = res
+                .add_message(msg)
+                .add_attribute("executed", "withdraw")
+                .add_attribute("token", token)
+                .add_attribute("amount", amount.to_string())
+                .add_attribute("recipient", recipient)
+                .add_attribute("penalty", penalty.to_string());
+        }
+        JointAction::Transfer { to, amount, token } => {
+            ensure_not_paused(deps.storage, Operation::Transfers)?;
+            ensure_not_frozen(deps.storage, &env, &account)?;
+            let data_from = accounts().load(deps.storage, &account)?;
+            let (fee, tier, penalty) =
+                transfer_funds(deps, &env, (&account, &data_from), &to, &token, amount)?;
+            res = res
+                .add_attribute("executed", "transfer")
+                .add_attribute("to", to)
+                .add_attribute("amount", amount.to_string())
+                .add_attribute("fee", fee.to_string())
+                .add_attribute("fee_tier", tier)
+                .add_attribute("token", token)
+                .add_attribute("penalty", penalty.to_string());
+        }
+        JointAction::WithdrawAndSend {
+            amount,
+            token,
+            contract,
+            msg,
+        } => {
+            ensure_not_paused(deps.storage, Operation::Withdrawals)?;
+            ensure_not_frozen(deps.storage, &env, &account)?;
+            let penalty = debit_balance(deps.storage, &env, &account, &token, amount, &contract)?;
+            let msg = send_to_contract(
+                deps.as_ref(),
+                &token,
+                &contract,
+                amount.checked_sub(penalty)?,
+                msg,
+            )?;
+            res = res
+                .add_message(msg)
+                .add_attribute("executed", "withdraw_and_send")
+                .add_attribute("token", token)
+                .add_attribute("amount", amount.to_string())
+                .add_attribute("contract", contract)
+                .add_attribute("penalty", penalty.to_string());
+        }
+    }
+
+    Ok(res)
+}
+
+fn load_owned_account(
+    deps: Deps,
+    sender: &Addr,
+    account: &str,
+) -> Result<AccountData, ContractError> {
+    let data = accounts().may_load(deps.storage, account)?;
+
+    if data.is_none() {
+        return Err(ContractError::AccountNotFound {
+            name: account.to_string(),
+        });
+    }
+
+    let data = data.unwrap();
+
+    if JOINT_ACCOUNTS.has(deps.storage, account) {
+        return Err(ContractError::JointAccount {
+            account: account.to_string(),
+        });
+    }
+
+    if &data.address != sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    Ok(data)
+}
+
+/// Checks `sender` may move `amount` out of an account right away: the owner of
+/// a single owner account, or a signer of a joint account within what is left
+/// of its limit in the current window, which the amount is counted against
+fn authorize_spend(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    account: &str,
+    amount: Uint128,
+) -> Result<AccountData, ContractError> {
+    let data = accounts().may_load(storage, account)?;
+
+    if data.is_none() {
+        return Err(ContractError::AccountNotFound {
+            name: account.to_string(),
+        });
+    }
+
+    let data = data.unwrap();
+
+    match JOINT_ACCOUNTS.may_load(storage, account)? {
+        Some(joint) => {
+            ensure_signer(&joint, sender)?;
+            let usage = JOINT_USAGE.may_load(storage, account)?;
+            let usage = match consume_window(&joint_spend_limit(&joint), usage, env, amount) {
+                Err(ContractError::RateLimitExceeded { .. }) => {
+                    return Err(ContractError::ApprovalRequired { limit: joint.limit })
+                }
+                usage => usage?,
+            };
+            JOINT_USAGE.save(storage, account, &usage)?;
+        }
+        None => {
+            if &data.address != sender {
+                return Err(ContractError::Unauthorized {});
+            }
+        }
+    }
+
+    Ok(data)
+}
+
+/// Makes `currency` the default token, accepting it for deposits if it was not already.
+/// Balances in the previous currency stay withdrawable by naming it explicitly.
+pub fn try_change_currency(
+    deps: DepsMut,
+    info: MessageInfo,
+    currency: String,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::Admin, &info.sender)?;
+
+    let currency = deps.api.addr_validate(&currency)?;
+    if config.currency == currency {
+        return Err(ContractError::SameCurrency {
+            currency: currency.to_string(),
+        });
+    }
+
+    TOKENS.save(deps.storage, &currency, &Empty {})?;
+    STATE.save(
+        deps.storage,
+        &State {
+            currency: currency.to_owned(),
+            ..config
+        },
+    )?;
+    Ok(Response::new()
+        .add_attribute("method", "change_currency")
+        .add_attribute("owner", info.sender)
+        .add_attribute("currency", currency))
+}
+
+pub fn try_add_native_token(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::Admin, &info.sender)?;
+
+    if denom.is_empty() {
+        return Err(ContractError::InvalidRequest("Empty denom".to_string()));
+    }
+    NATIVE_TOKENS.save(deps.storage, &denom, &Empty {})?;
+
+    Ok(Response::new()
+        .add_attribute("method", "add_native_token")
+        .add_attribute("owner", info.sender)
+        .add_attribute("token", denom))
+}
+
+pub fn try_add_token(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: String,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::Admin, &info.sender)?;
+
+    let token = deps.api.addr_validate(&token)?;
+    TOKENS.save(deps.storage, &token, &Empty {})?;
+
+    Ok(Response::new()
+        .add_attribute("method", "add_token")
+        .add_attribute("owner", info.sender)
+        .add_attribute("token", token))
+}
+
+/// Stops accepting a cw20 `token` or native denom, which requires every account
+/// and the treasury to be drained of it
+pub fn try_remove_token(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: String,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::Admin, &info.sender)?;
+
+    let token = resolve_token(deps.as_ref(), Some(token))?;
+    if config.currency == token {
+        return Err(ContractError::InvalidRequest(
+            "Cannot remove the default currency".to_string(),
+        ));
+    }
+
+    let total = TOTAL_BALANCES
+        .may_load(deps.storage, &token)?
+        .unwrap_or_default();
+    if !total.is_zero() {
+        return Err(ContractError::OutstandingBalances { total });
+    }
+    let treasury = TREASURY.may_load(deps.storage, &token)?.unwrap_or_default();
+    if !treasury.is_zero() {
+        return Err(ContractError::OutstandingFees { amount: treasury });
+    }
+
+    if NATIVE_TOKENS.has(deps.storage, &token) {
+        NATIVE_TOKENS.remove(deps.storage, &token);
+    } else {
+        TOKENS.remove(deps.storage, &Addr::unchecked(&token));
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "remove_token")
+        .add_attribute("owner", info.sender)
+        .add_attribute("token", token))
+}
+
+/// Normalizes a token to its storage key: native denoms are kept as they are,
+/// anything else must be a cw20 contract address
+fn token_key(deps: Deps, token: String) -> StdResult<String> {
+    if NATIVE_TOKENS.has(deps.storage, &token) {
+        Ok(token)
+    } else {
+        Ok(deps.api.addr_validate(&token)?.to_string())
+    }
+}
+
+/// Resolves the token of a request, defaulting to the configured currency
+fn resolve_token(deps: Deps, token: Option<String>) -> Result<String, ContractError> {
+    match token {
+        None => Ok(STATE.load(deps.storage)?.currency.to_string()),
+        Some(token) => {
+            let token = token_key(deps, token)?;
+            if !NATIVE_TOKENS.has(deps.storage, &token)
+                && !TOKENS.has(deps.storage, &Addr::unchecked(&token))
+            {
+                return Err(ContractError::UnsupportedToken { token });
+            }
+            Ok(token)
+        }
+    }
+}
+
+/// Builds the message paying `amount` of `token` out of the bank to `recipient`
+fn payout(deps: Deps, token: &str, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    if NATIVE_TOKENS.has(deps.storage, token) {
+        Ok(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins(amount.u128(), token),
+        }
+        .into())
+    } else {
+        Cw20Contract(Addr::unchecked(token)).call(Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+        })
+    }
+}
+
+fn load_balance(storage: &dyn Storage, account: &str, token: &str) -> StdResult<Uint128> {
+    Ok(BALANCES
+        .may_load(storage, (account, token))?
+        .unwrap_or_default())
+}
+
+/// Adds a deposit of `depositor` to an account
+fn credit_balance(
+    storage: &mut dyn Storage,
+    env: &Env,
+    account: &str,
+    token: &str,
+    amount: Uint128,
+    depositor: &str,
+) -> Result<(), ContractError> {
+    ledger::post(
+        storage,
+        token,
+        &[
+            Posting::Debit(Book::External, amount),
+            Posting::Credit(Book::Account(account), amount),
+        ],
+    )?;
+    Ok(journal(
+        storage,
+        env,
+        account,
+        EntryKind::Deposit,
+        depositor,
+        (token, amount, Uint128::zero()),
+    )?)
+}
+
+/// Starts the statement of a new account after every entry written so far, so
+/// a reused name does not show the history of the account closed before it
+fn open_journal(storage: &mut dyn Storage, account: &str) -> StdResult<()> {
+    let start = JOURNAL_SEQ.may_load(storage)?.unwrap_or_default();
+    JOURNAL_START.save(storage, account, &start)
+}
+
+/// Appends an entry to the statement of `account`
+fn journal(
+    storage: &mut dyn Storage,
+    env: &Env,
+    account: &str,
+    kind: EntryKind,
+    counterparty: &str,
+    (token, amount, fee): (&str, Uint128, Uint128),
+) -> StdResult<()> {
+    let id = JOURNAL_SEQ.may_load(storage)?.unwrap_or_default() + 1;
+    JOURNAL_SEQ.save(storage, &id)?;
+    JOURNAL.save(
+        storage,
+        (account, id),
+        &JournalEntry {
+            id,
+            kind,
+            counterparty: counterparty.to_string(),
+            token: token.to_string(),
+            amount,
+            fee,
+            height: env.block.height,
+            time: env.block.time,
+        },
+    )
+}
+
+pub fn try_withdraw_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: String,
+    amount: Uint128,
+    token: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::FeeManager, &info.sender)?;
+
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let token = resolve_token(deps.as_ref(), token)?;
+    ledger::post(
+        deps.storage,
+        &token,
+        &[
+            Posting::Debit(Book::Treasury, amount),
+            Posting::Credit(Book::External, amount),
+        ],
+    )?;
+
+    let msg = payout(deps.as_ref(), &token, &recipient, amount)?;
+
+    Ok(Response::new()
+        .add_message(msg)
+        .add_attribute("method", "withdraw_fees")
+        .add_attribute("owner", info.sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("token", token)
+        .add_attribute("amount", amount.to_string()))
+}
+
+/// Books the tokens no account or the treasury accounts for into the treasury
+pub fn try_reconcile_surplus(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::Admin, &info.sender)?;
+
+    let token = resolve_token(deps.as_ref(), token)?;
+    let reserves = reserves(deps.as_ref(), &env, token)?;
+
+    if reserves.surplus.is_zero() {
+        return Err(ContractError::InvalidRequest(
+            "No surplus to reconcile".to_string(),
+        ));
+    }
+
+    ledger::post(
+        deps.storage,
+        &reserves.token,
+        &[
+            Posting::Debit(Book::External, reserves.surplus),
+            Posting::Credit(Book::Treasury, reserves.surplus),
+        ],
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "reconcile_surplus")
+        .add_attribute("owner", info.sender)
+        .add_attribute("token", reserves.token)
+        .add_attribute("amount", reserves.surplus.to_string()))
+}
+
+/// Sends back tokens that reached the contract outside of a deposit, leaving
+/// the account balances and the treasury of accepted tokens backed
+pub fn try_recover_tokens(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token: Denom,
+    amount: Uint128,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::Admin, &info.sender)?;
+
+    if amount.is_zero() {
+        return Err(ContractError::ZeroAmount {});
+    }
+
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let (key, accepted) = match &token {
+        Denom::Native(denom) => (denom.clone(), NATIVE_TOKENS.has(deps.storage, denom)),
+        Denom::Cw20(addr) => {
+            let addr = deps.api.addr_validate(addr.as_str())?;
+            (addr.to_string(), TOKENS.has(deps.storage, &addr))
+        }
+    };
+
+    if accepted {
+        let surplus = reserves(deps.as_ref(), &env, key.clone())?.surplus;
+        if amount > surplus {
+            return Err(ContractError::ExceedsSurplus { surplus });
+        }
+    }
+
+    let msg: CosmosMsg = match token {
+        Denom::Native(denom) => BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins(amount.u128(), denom),
+        }
+        .into(),
+        Denom::Cw20(_) => Cw20Contract(Addr::unchecked(&key)).call(Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+        })?,
+    };
+
+    Ok(Response::new()
+        .add_message(msg)
+        .add_attribute("method", "recover_tokens")
+        .add_attribute("owner", info.sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("token", key)
+        .add_attribute("amount", amount.to_string()))
+}
+
+pub fn try_update_fee_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    fee: FeeConfig,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::FeeManager, &info.sender)?;
+
+    validate_fee_config(&fee)?;
+
+    let res = Response::new()
+        .add_attribute("method", "update_fee_config")
+        .add_attribute("owner", info.sender)
+        .add_attribute("rate", fee.rate.to_string());
+
+    STATE.save(deps.storage, &State { fee, ..config })?;
+
+    Ok(res)
+}
+
+/// Rejects fee schedules that could take more than the transferred amount
+/// or whose caps contradict each other
+fn validate_fee_config(fee: &FeeConfig) -> Result<(), ContractError> {
+    validate_rate(fee.rate)?;
+    for band in fee.bands.iter() {
+        validate_rate(band.rate)?;
+    }
+    if let (Some(min), Some(max)) = (fee.min, fee.max) {
+        if min > max {
+            return Err(ContractError::InvalidRequest(
+                "Minimum fee cannot exceed maximum fee".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn try_set_account_fee_tier(
+    deps: DepsMut,
+    info: MessageInfo,
+    account: String,
+    tier: Option<FeeTier>,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::FeeManager, &info.sender)?;
+
+    if accounts().may_load(deps.storage, &account)?.is_none() {
+        return Err(ContractError::AccountNotFound {
+            name: account.clone(),
+        });
+    }
+
+    let res = Response::new()
+        .add_attribute("method", "set_account_fee_tier")
+        .add_attribute("owner", info.sender)
+        .add_attribute("account", account.to_owned());
+
+    match tier {
+        Some(tier) => {
+            validate_rate(tier.rate)?;
+            ACCOUNT_FEE_TIERS.save(deps.storage, &account, &tier)?;
+            Ok(res.add_attribute("tier", tier.name))
+        }
+        None => {
+            ACCOUNT_FEE_TIERS.remove(deps.storage, &account);
+            Ok(res)
+        }
+    }
+}
+
+pub fn try_set_owner_fee_tier(
+    deps: DepsMut,
+    info: MessageInfo,
+    owner: String,
+    tier: Option<FeeTier>,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::FeeManager, &info.sender)?;
+
+    let owner = deps.api.addr_validate(&owner)?;
+
+    let res = Response::new()
+        .add_attribute("method", "set_owner_fee_tier")
+        .add_attribute("owner", info.sender)
+        .add_attribute("address", owner.to_owned());
+
+    match tier {
+        Some(tier) => {
+            validate_rate(tier.rate)?;
+            OWNER_FEE_TIERS.save(deps.storage, &owner, &tier)?;
+            Ok(res.add_attribute("tier", tier.name))
+        }
+        None => {
+            OWNER_FEE_TIERS.remove(deps.storage, &owner);
+            Ok(res)
+        }
+    }
+}
+
+fn validate_rate(rate: Decimal) -> Result<(), ContractError> {
+    if rate > Decimal::one() {
+        return Err(ContractError::InvalidRequest(
+            "Fee rate cannot exceed 100%".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The tier of an account, falling back to the tier of its owner
+fn load_fee_tier(deps: Deps, account: &str, data: &AccountData) -> StdResult<Option<FeeTier>> {
+    match ACCOUNT_FEE_TIERS.may_load(deps.storage, account)? {
+        Some(tier) => Ok(Some(tier)),
+        None => OWNER_FEE_TIERS.may_load(deps.storage, &data.address),
+    }
+}
+
+/// Computes the fee of a transfer and the name of the tier it was charged at.
+///
+/// The amount bands pick the standard rate, and the sender or recipient tier
+/// replaces it when cheaper. The minimum fee only applies to the standard
+/// rate so exempt tiers really pay nothing.
+fn resolve_fee(
+    deps: Deps,
+    from: &str,
+    data_from: &AccountData,
+    to: &str,
+    data_to: &AccountData,
+    amount: Uint128,
+) -> StdResult<(Uint128, String)> {
+    let fee = STATE.load(deps.storage)?.fee;
+
+    let mut rate = fee.rate;
+    let mut tier = "standard".to_string();
+    let mut floor = fee.min;
+    if let Some(band) = fee
+        .bands
+        .iter()
+        .filter(|band| band.from <= amount)
+        .max_by_key(|band| band.from)
+    {
+        rate = band.rate;
+        tier = format!("band_{}", band.from);
+    }
+
+    let tiers = vec![
+        load_fee_tier(deps, from, data_from)?,
+        load_fee_tier(deps, to, data_to)?,
+    ];
+    for candidate in tiers.into_iter().flatten() {
+        if candidate.rate < rate {
+            rate = candidate.rate;
+            tier = candidate.name;
+            floor = None;
+        }
+    }
+
+    let mut charged = amount * rate;
+    if let Some(min) = floor {
+        charged = charged.max(min);
+    }
+    if let Some(max) = fee.max {
+        charged = charged.min(max);
+    }
+    Ok((charged.min(amount), tier))
+}
+
+/// Only the bank owner may pass, nobody does once ownership is renounced
+fn ensure_owner(config: &State, sender: &Addr) -> Result<(), ContractError> {
+    if config.owner.as_ref() != Some(sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+/// Passes for holders of `role` and for the owner, who implicitly holds every role
+fn ensure_role(
+    storage: &dyn Storage,
+    config: &State,
+    role: Role,
+    sender: &Addr,
+) -> Result<(), ContractError> {
+    if config.owner.as_ref() == Some(sender) || ROLES.has(storage, (role.as_str(), sender)) {
+        return Ok(());
+    }
+    Err(ContractError::Unauthorized {})
+}
+
+/// Halts or resumes the given operations, every operation if none are given
+pub fn try_set_paused(
+    deps: DepsMut,
+    info: MessageInfo,
+    operations: Option<Vec<Operation>>,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    let mut config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::Pauser, &info.sender)?;
+
+    let operations = operations.unwrap_or_else(Operation::all);
+    for operation in operations.iter() {
+        config.paused.set(operation, paused);
+    }
+    STATE.save(deps.storage, &config)?;
+
+    let operations = operations
+        .iter()
+        .map(|operation| operation.as_str())
+        .collect::<Vec<_>>();
+
+    Ok(Response::new()
+        .add_attribute("method", if paused { "pause" } else { "unpause" })
+        .add_attribute("owner", info.sender)
+        .add_attribute("operations", operations.join(",")))
+}
+
+fn ensure_not_paused(storage: &dyn Storage, operation: Operation) -> Result<(), ContractError> {
+    if STATE.load(storage)?.paused.is_paused(&operation) {
+        return Err(ContractError::Paused {
+            operation: operation.as_str().to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Blocks withdrawals and outgoing transfers of an account until it is
+/// unfrozen or `expires` passes, deposits still go through
+pub fn try_freeze_account(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    account: String,
+    reason: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::Compliance, &info.sender)?;
+
+    if accounts().may_load(deps.storage, &account)?.is_none() {
+        return Err(ContractError::AccountNotFound {
+            name: account.clone(),
+        });
+    }
+
+    if let Some(expires) = expires {
+        if expires.is_expired(&env.block) {
+            return Err(ContractError::Expired {});
+        }
+    }
+
+    FROZEN.save(
+        deps.storage,
+        &account,
+        &Freeze {
+            reason: reason.to_owned(),
+            expires,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "freeze_account")
+        .add_attribute("owner", info.sender)
+        .add_attribute("address", account)
+        .add_attribute("reason", reason))
+}
+
+pub fn try_unfreeze_account(
+    deps: DepsMut,
+    info: MessageInfo,
+    account: String,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::Compliance, &info.sender)?;
+
+    if !FROZEN.has(deps.storage, &account) {
+        return Err(ContractError::InvalidRequest(
+            "Account is not frozen".to_string(),
+        ));
+    }
+    FROZEN.remove(deps.storage, &account);
+
+    Ok(Response::new()
+        .add_attribute("method", "unfreeze_account")
+        .add_attribute("owner", info.sender)
+        .add_attribute("address", account))
+}
+
+fn ensure_not_frozen(storage: &dyn Storage, env: &Env, account: &str) -> Result<(), ContractError> {
+    if let Some(freeze) = FROZEN.may_load(storage, account)? {
+        if !freeze.is_expired(&env.block) {
+            return Err(ContractError::AccountFrozen {
+                account: account.to_string(),
+                reason: freeze.reason,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Caps what an account can withdraw or transfer to other owners per window,
+/// or lifts the cap with `None` (admin only)
+pub fn try_set_account_rate_limit(
+    deps: DepsMut,
+    info: MessageInfo,
+    account: String,
+    token: Option<String>,
+    limit: Option<RateLimit>,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::Admin, &info.sender)?;
+
+    if accounts().may_load(deps.storage, &account)?.is_none() {
+        return Err(ContractError::AccountNotFound {
+            name: account.clone(),
+        });
+    }
+
+    let token = resolve_token(deps.as_ref(), token)?;
+    let key = (account.as_str(), token.as_str());
+    match &limit {
+        Some(limit) => {
+            validate_rate_limit(limit)?;
+            ACCOUNT_RATE_LIMITS.save(deps.storage, key, limit)?;
+        }
+        None => {
+            ACCOUNT_RATE_LIMITS.remove(deps.storage, key);
+            ACCOUNT_USAGE.remove(deps.storage, key);
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "set_account_rate_limit")
+        .add_attribute("owner", info.sender)
+        .add_attribute("address", account)
+        .add_attribute("token", token))
+}
+
+/// Caps what leaves the bank in a token per window across all accounts,
+/// or lifts the cap with `None` (admin only)
+pub fn try_set_global_rate_limit(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: Option<String>,
+    limit: Option<RateLimit>,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_role(deps.storage, &config, Role::Admin, &info.sender)?;
+
+    let token = resolve_token(deps.as_ref(), token)?;
+    match &limit {
+        Some(limit) => {
+            validate_rate_limit(limit)?;
+            GLOBAL_RATE_LIMITS.save(deps.storage, &token, limit)?;
+        }
+        None => {
+            GLOBAL_RATE_LIMITS.remove(deps.storage, &token);
+            GLOBAL_USAGE.remove(deps.storage, &token);
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "set_global_rate_limit")
+        .add_attribute("owner", info.sender)
+        .add_attribute("token", token))
+}
+
+fn validate_rate_limit(limit: &RateLimit) -> Result<(), ContractError> {
+    if limit.window == 0 {
+        return Err(ContractError::InvalidRequest(
+            "Rate limit window must not be zero".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Drops the limits and window usage of an account in every token
+fn clear_rate_limits(storage: &mut dyn Storage, account: &str) -> StdResult<()> {
+    let tokens = ACCOUNT_RATE_LIMITS
+        .prefix(account)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for token in tokens {
+        ACCOUNT_RATE_LIMITS.remove(storage, (account, &token));
+    }
+    let tokens = ACCOUNT_USAGE
+        .prefix(account)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for token in tokens {
+        ACCOUNT_USAGE.remove(storage, (account, &token));
+    }
+    Ok(())
+}
+
+/// Counts an outflow against the limit of the account and, when it leaves the
+/// bank, against the global limit of the token
+fn consume_rate_limits(
+    storage: &mut dyn Storage,
+    env: &Env,
+    account: &str,
+    token: &str,
+    amount: Uint128,
+    leaves_bank: bool,
+) -> Result<(), ContractError> {
+    if let Some(limit) = ACCOUNT_RATE_LIMITS.may_load(storage, (account, token))? {
+        let usage = ACCOUNT_USAGE.may_load(storage, (account, token))?;
+        let usage = consume_window(&limit, usage, env, amount)?;
+        ACCOUNT_USAGE.save(storage, (account, token), &usage)?;
+    }
+
+    if leaves_bank {
+        if let Some(limit) = GLOBAL_RATE_LIMITS.may_load(storage, token)? {
+            let usage = GLOBAL_USAGE.may_load(storage, token)?;
+            let usage = consume_window(&limit, usage, env, amount)?;
+            GLOBAL_USAGE.save(storage, token, &usage)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds `amount` to the current window, starting a new one if the last has passed
+fn consume_window(
+    limit: &RateLimit,
+    usage: Option<WindowUsage>,
+    env: &Env,
+    amount: Uint128,
+) -> Result<WindowUsage, ContractError> {
+    let mut usage = current_window(limit, usage, env);
+    let remaining = limit.amount.saturating_sub(usage.spent);
+
+    if amount > remaining {
+        return Err(ContractError::RateLimitExceeded { remaining });
+    }
+
+    usage.spent = usage.spent.checked_add(amount)?;
+    Ok(usage)
+}
+
+fn current_window(limit: &RateLimit, usage: Option<WindowUsage>, env: &Env) -> WindowUsage {
+    match usage {
+        Some(usage) if env.block.time < usage.start.plus_seconds(limit.window) => usage,
+        _ => WindowUsage {
+            start: env.block.time,
+            spent: Uint128::zero(),
+        },
+    }
+}
+
+pub fn try_grant_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    address: String,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_owner(&config, &info.sender)?;
+
+    let address = deps.api.addr_validate(&address)?;
+    ROLES.save(deps.storage, (role.as_str(), &address), &Empty {})?;
+
+    Ok(Response::new()
+        .add_attribute("method", "grant_role")
+        .add_attribute("owner", info.sender)
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", address))
+}
+
+pub fn try_revoke_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    address: String,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_owner(&config, &info.sender)?;
+
+    let address = deps.api.addr_validate(&address)?;
+    if !ROLES.has(deps.storage, (role.as_str(), &address)) {
+        return Err(ContractError::InvalidRequest(
+            "Address does not hold the role".to_string(),
+        ));
+    }
+    ROLES.remove(deps.storage, (role.as_str(), &address));
+
+    Ok(Response::new()
+        .add_attribute("method", "revoke_role")
+        .add_attribute("owner", info.sender)
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", address))
+}
+
+/// Offers the ownership of the bank to `new_owner`, who has to accept it before `expiry`
+pub fn try_propose_owner(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_owner: String,
+    expiry: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_owner(&config, &info.sender)?;
+
+    let new_owner = deps.api.addr_validate(&new_owner)?;
+    if let Some(expiry) = expiry {
+        if expiry.is_expired(&env.block) {
+            return Err(ContractError::OwnershipProposalExpired {});
+        }
+    }
+
+    PENDING_OWNER.save(
+        deps.storage,
+        &PendingOwner {
+            new_owner: new_owner.to_owned(),
+            expiry,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "propose_owner")
+        .add_attribute("owner", info.sender)
+        .add_attribute("new_owner", new_owner))
+}
+
+pub fn try_accept_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let pending = PENDING_OWNER.may_load(deps.storage)?;
+
+    if pending.is_none() {
+        return Err(ContractError::NoPendingOwner {});
+    }
+
+    let pending = pending.unwrap();
+
+    if pending.new_owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(expiry) = pending.expiry {
+        if expiry.is_expired(&env.block) {
+            return Err(ContractError::OwnershipProposalExpired {});
+        }
+    }
+
+    STATE.update(deps.storage, |config| -> StdResult<_> {
+        Ok(State {
+            owner: Some(pending.new_owner),
+            ..config
+        })
+    })?;
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("method", "accept_ownership")
+        .add_attribute("owner", info.sender))
+}
+
+pub fn try_cancel_ownership_proposal(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_owner(&config, &info.sender)?;
+
+    if PENDING_OWNER.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::NoPendingOwner {});
+    }
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("method", "cancel_ownership_proposal")
+        .add_attribute("owner", info.sender))
+}
+
+/// Gives up ownership for good, leaving every owner only message unusable
+pub fn try_renounce_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let config = STATE.load(deps.storage)?;
+    ensure_owner(&config, &info.sender)?;
+
+    STATE.save(
+        deps.storage,
+        &State {
+            owner: None,
+            ..config
+        },
+    )?;
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("method", "renounce_ownership")
+        .add_attribute("owner", info.sender))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetAccount { address } => to_binary(&query_account(deps, address)?),
+        QueryMsg::GetBalance { account, token } => {
+            to_binary(&query_balance(deps, env, account, token)?)
+        }
+        QueryMsg::GetHistory {
+            account,
+            start_after,
+            limit,
+        } => to_binary(&query_history(deps, account, start_after, limit)?),
+        QueryMsg::GetTreasury { token } => to_binary(&query_treasury(deps, token)?),
+        QueryMsg::Reserves { token } => to_binary(&query_reserves(deps, env, token)?),
+        QueryMsg::GetConfig {} => to_binary(&query_config(deps)?),
+        QueryMsg::GetTokens {} => to_binary(&query_tokens(deps)?),
+        QueryMsg::GetOwnership {} => to_binary(&query_ownership(deps)?),
+        QueryMsg::GetPauseState {} => to_binary(&query_pause_state(deps)?),
+        QueryMsg::ListFrozenAccounts { start_after, limit } => {
+            to_binary(&query_list_frozen_accounts(deps, env, start_after, limit)?)
+        }
+        QueryMsg::GetRateLimit { account, token } => {
+            to_binary(&query_rate_limit(deps, env, account, token)?)
+        }
+        QueryMsg::Roles { role } => to_binary(&query_roles(deps, role)?),
+        QueryMsg::ListAccounts { start_after, limit } => {
+            to_binary(&query_list_accounts(deps, start_after, limit)?)
+        }
+        QueryMsg::ListAccountsByOwner {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&query_list_accounts_by_owner(
+            deps,
+            owner,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::Allowance {
+            account,
+            spender,
+            token,
+        } => to_binary(&query_allowance(deps, account, spender, token)?),
+        QueryMsg::AllAllowances {
+            account,
+            token,
+            start_after,
+            limit,
+        } => to_binary(&query_all_allowances(
+            deps,
+            account,
+            token,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::GetJointAccount { account } => to_binary(&query_joint_account(deps, account)?),
+        QueryMsg::GetProposal { proposal_id } => to_binary(&query_proposal(deps, proposal_id)?),
+        QueryMsg::ListProposals { start_after, limit } => {
+            to_binary(&query_list_proposals(deps, start_after, limit)?)
+        }
+    }
+}
+
+pub fn query_account(deps: Deps, address: String) -> StdResult<AccountResponse> {
+    let addr = deps.api.addr_validate(address.as_str())?;
+    let account = accounts()
+        .idx
+        .address
+        .prefix(addr)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    if !account.is_empty() {
+        Ok(AccountResponse { account })
+    } else {
+        Err(StdError::NotFound {
+            kind: "account".to_string(),
+        })
+    }
+}
+
+pub fn query_balance(
+    deps: Deps,
+    env: Env,
+    account: String,
+    token: Option<String>,
+) -> StdResult<BalanceResponse> {
+    let data = accounts().may_load(deps.storage, &account)?;
+
+    if data.is_none() {
+        return Err(StdError::NotFound {
+            kind: "balance".to_string(),
+        });
+    }
+
+    let locked_until = match data.unwrap().kind {
+        AccountKind::TimeLocked { lock_until, .. } => Some(lock_until),
+        AccountKind::Standard => None,
+    };
+    let locked = matches!(locked_until, Some(until) if env.block.time < until);
+
+    match token {
+        Some(token) => {
+            let token = token_key(deps, token)?;
+            let balance = load_balance(deps.storage, &account, &token)?;
+            Ok(BalanceResponse {
+                balance,
+                holdings: vec![TokenBalance {
+                    token,
+                    amount: balance,
+                }],
+                locked_until,
+                locked,
+            })
+        }
+        None => {
+            let currency = STATE.load(deps.storage)?.currency.to_string();
+            Ok(BalanceResponse {
+                balance: load_balance(deps.storage, &account, &currency)?,
+                holdings: load_holdings(deps.storage, &account)?,
+                locked_until,
+                locked,
+            })
+        }
+    }
+}
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Lists every account in name order
+pub fn query_list_accounts(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListAccountsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    let accounts = accounts()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (name, data) = item?;
+            account_info(deps.storage, name, data)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ListAccountsResponse { accounts })
+}
+
+/// Lists the accounts of `owner` in name order
+pub fn query_list_accounts_by_owner(
+    deps: Deps,
+    owner: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListAccountsResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let accounts = accounts()
+        .idx
+        .address
+        .prefix(owner)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (name, data) = item?;
+            account_info(deps.storage, name, data)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ListAccountsResponse { accounts })
+}
+
+pub fn query_allowance(
+    deps: Deps,
+    account: String,
+    spender: String,
+    token: Option<String>,
+) -> StdResult<AllowanceResponse> {
+    let spender = deps.api.addr_validate(&spender)?;
+    let token = match token {
+        Some(token) => token_key(deps, token)?,
+        None => STATE.load(deps.storage)?.currency.to_string(),
+    };
+    let allowance = ALLOWANCES
+        .may_load(deps.storage, (&account, &token, &spender))?
+        .unwrap_or_default();
+    Ok(allowance)
+}
+
+/// Lists the allowances granted on an account in one token, in spender order
+pub fn query_all_allowances(
+    deps: Deps,
+    account: String,
+    token: Option<String>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AllAllowancesResponse> {
+    let token = match token {
+        Some(token) => token_key(deps, token)?,
+        None => STATE.load(deps.storage)?.currency.to_string(),
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after.map(Addr::unchecked);
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    let allowances = ALLOWANCES
+        .prefix((&account, &token))
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(spender, allow)| AllowanceInfo {
+                spender: spender.to_string(),
+                allowance: allow.allowance,
+                expires: allow.expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(AllAllowancesResponse { allowances })
+}
+
+pub fn query_joint_account(deps: Deps, account: String) -> StdResult<JointAccountResponse> {
+    let joint = JOINT_ACCOUNTS.load(deps.storage, &account)?;
+    Ok(JointAccountResponse {
+        signers: joint.signers,
+        threshold: joint.threshold,
+        limit: joint.limit,
+        voting_period: joint.voting_period,
+    })
+}
+
+pub fn query_proposal(deps: Deps, proposal_id: u64) -> StdResult<ProposalResponse> {
+    let proposal = PROPOSALS.load(deps.storage, proposal_id)?;
+    Ok(proposal_response(proposal_id, proposal))
+}
+
+/// Lists the proposals still waiting for approvals, oldest first
+pub fn query_list_proposals(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ListProposalsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let proposals = PROPOSALS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(id, proposal)| proposal_response(id, proposal)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ListProposalsResponse { proposals })
+}
+
+fn proposal_response(id: u64, proposal: Proposal) -> ProposalResponse {
+    ProposalResponse {
+        id,
+        account: proposal.account,
+        action: proposal.action,
+        approvals: proposal.approvals,
+        expires: proposal.expires,
+    }
+}
+
+pub fn query_pause_state(deps: Deps) -> StdResult<PauseState> {
+    Ok(STATE.load(deps.storage)?.paused)
+}
+
+/// Lists the granted roles, the owner implicitly holding all of them is not listed
+pub fn query_roles(deps: Deps, role: Option<Role>) -> StdResult<RolesResponse> {
+    let roles = match role {
+        Some(role) => vec![role],
+        None => Role::all(),
+    };
+
+    let mut grants = vec![];
+    for role in roles {
+        for address in ROLES
+            .prefix(role.as_str())
+            .keys(deps.storage, None, None, Order::Ascending)
+        {
+            grants.push(RoleGrant {
+                role: role.to_owned(),
+                address: address?,
+            });
+        }
+    }
+
+    Ok(RolesResponse { grants })
+}
+
+/// Lists the accounts under a compliance hold that has not expired, in name order
+pub fn query_list_frozen_accounts(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<FrozenAccountsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    let accounts = FROZEN
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((_, freeze)) if freeze.is_expired(&env.block)))
+        .take(limit)
+        .map(|item| {
+            item.map(|(account, freeze)| FrozenAccount {
+                account,
+                reason: freeze.reason,
+                expires: freeze.expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(FrozenAccountsResponse { accounts })
+}
+
+/// Reports the withdrawal limits of an account and what is left of them in the current window
+pub fn query_rate_limit(
+    deps: Deps,
+    env: Env,
+    account: String,
+    token: Option<String>,
+) -> StdResult<RateLimitResponse> {
+    let token = match token {
+        Some(token) => token_key(deps, token)?,
+        None => STATE.load(deps.storage)?.currency.to_string(),
+    };
+
+    let limit = ACCOUNT_RATE_LIMITS.may_load(deps.storage, (&account, &token))?;
+    let remaining = match &limit {
+        Some(limit) => {
+            let usage = ACCOUNT_USAGE.may_load(deps.storage, (&account, &token))?;
+            let usage = current_window(limit, usage, &env);
+            Some(limit.amount.saturating_sub(usage.spent))
+        }
+        None => None,
+    };
+
+    let global_limit = GLOBAL_RATE_LIMITS.may_load(deps.storage, &token)?;
+    let global_remaining = match &global_limit {
+        Some(limit) => {
+            let usage = GLOBAL_USAGE.may_load(deps.storage, &token)?;
+            let usage = current_window(limit, usage, &env);
+            Some(limit.amount.saturating_sub(usage.spent))
+        }
+        None => None,
+    };
+
+    Ok(RateLimitResponse {
+        limit,
+        remaining,
+        global_limit,
+        global_remaining,
+    })
+}
+
+/// Lists the statement of an account, newest entry first
+pub fn query_history(
+    deps: Deps,
+    account: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<HistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = JOURNAL_START
+        .may_load(deps.storage, &account)?
+        .unwrap_or_default();
+    let end = start_after.map(Bound::exclusive);
+
+    let entries = JOURNAL
+        .prefix(&account)
+        .range(
+            deps.storage,
+            Some(Bound::exclusive(start)),
+            end,
+            Order::Descending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, entry)| entry))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(HistoryResponse { entries })
+}
+
+fn account_info(storage: &dyn Storage, name: String, data: AccountData) -> StdResult<AccountInfo> {
+    Ok(AccountInfo {
+        balances: load_holdings(storage, &name)?,
+        pending_owner: PENDING_ACCOUNT_OWNERS.may_load(storage, &name)?,
+        name,
+        owner: data.address,
+    })
+}
+
+/// Every non-zero balance of an account
+fn load_holdings(storage: &dyn Storage, account: &str) -> StdResult<Vec<TokenBalance>> {
+    BALANCES
+        .prefix(account)
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((_, amount)) if amount.is_zero()))
+        .map(|item| item.map(|(token, amount)| TokenBalance { token, amount }))
+        .collect()
+}
+
+pub fn query_treasury(deps: Deps, token: Option<String>) -> StdResult<TreasuryResponse> {
+    let token = match token {
+        Some(token) => token_key(deps, token)?,
+        None => STATE.load(deps.storage)?.currency.to_string(),
+    };
+    let balance = TREASURY.may_load(deps.storage, &token)?.unwrap_or_default();
+    Ok(TreasuryResponse { balance })
+}
+
+pub fn query_reserves(deps: Deps, env: Env, token: Option<String>) -> StdResult<ReservesResponse> {
+    let token = match token {
+        Some(token) => token_key(deps, token)?,
+        None => STATE.load(deps.storage)?.currency.to_string(),
+    };
+    reserves(deps, &env, token)
+}
+
+/// Lends the querier behind a `QuerierWrapper` to helpers taking a sized `Querier`,
+/// such as `Cw20Contract::balance`
+struct QuerierRef<'a>(&'a dyn Querier);
+
+impl Querier for QuerierRef<'_> {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        self.0.raw_query(bin_request)
+    }
+}
+
+fn reserves(deps: Deps, env: &Env, token: String) -> StdResult<ReservesResponse> {
+    let liabilities = TOTAL_BALANCES
+        .may_load(deps.storage, &token)?
+        .unwrap_or_default();
+    let treasury = TREASURY.may_load(deps.storage, &token)?.unwrap_or_default();
+    let assets = if NATIVE_TOKENS.has(deps.storage, &token) {
+        deps.querier
+            .query_balance(&env.contract.address, &token)?
+            .amount
+    } else {
+        Cw20Contract(Addr::unchecked(&token)).balance::<_, _, Empty>(
+            &QuerierRef(deps.querier.deref()),
+            env.contract.address.clone(),
+        )?
+    };
+    let owed = liabilities.checked_add(treasury)?;
+
+    Ok(ReservesResponse {
+        surplus: assets.saturating_sub(owed),
+        solvent: assets >= owed,
+        token,
+        liabilities,
+        treasury,
+        assets,
+    })
+}
+
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = STATE.load(deps.storage)?;
+    Ok(ConfigResponse {
+        owner: config.owner,
+        currency: config.currency,
+        fee: config.fee,
+    })
+}
+
+pub fn query_tokens(deps: Deps) -> StdResult<TokensResponse> {
+    let tokens = TOKENS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let native = NATIVE_TOKENS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(TokensResponse { tokens, native })
+}
+
+pub fn query_ownership(deps: Deps) -> StdResult<OwnershipResponse> {
+    let owner = STATE.load(deps.storage)?.owner;
+    let pending = PENDING_OWNER.may_load(deps.storage)?;
+    Ok(OwnershipResponse {
+        owner,
+        pending_owner: pending.as_ref().map(|pending| pending.new_owner.to_owned()),
+        pending_expiry: pending.and_then(|pending| pending.expiry),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::{LegacyBalanceData, LEGACY_BALANCES, LEGACY_OWNER};
+    use crate::state::FeeBand;
+    use cosmwasm_std::from_binary;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+        MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{ContractResult, OwnedDeps, SystemError, SystemResult, WasmQuery};
+    use cw20::Cw20QueryMsg;
+    use cw_storage_plus::Map;
+
+    /// Answers cw20 `Balance` queries about the contract with `balance`
+    fn mock_cw20_balance(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, balance: u128) {
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::Smart { msg, .. } => match from_binary(msg) {
+                Ok(Cw20QueryMsg::Balance { address }) if address == MOCK_CONTRACT_ADDR => {
+                    SystemResult::Ok(ContractResult::Ok(
+                        to_binary(&cw20::BalanceResponse {
+                            balance: Uint128::new(balance),
+                        })
+                        .unwrap(),
+                    ))
+                }
+                _ => SystemResult::Err(SystemError::InvalidRequest {
+                    error: String::from("unexpected cw20 query"),
+                    request: msg.clone(),
+                }),
+            },
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: String::from("wasm"),
+            }),
+        });
+    }
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let info = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+    }
+
+    #[test]
+    fn proper_add_account_and_deposit() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // user 1 initializes the contract
+
+        let user = mock_info("user1", &[]);
+
+        // user 1 adds an account
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+
+        let msg = QueryMsg::GetAccount {
+            address: user.sender.to_owned().to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: AccountResponse = from_binary(&res).unwrap();
+        assert_eq!(value.account.len(), 1);
+        assert_eq!(value.account, vec!["Account 1"]);
+
+        // user 1 add more accounts
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 2"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+
+        let msg = QueryMsg::GetAccount {
+            address: user.sender.to_owned().to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: AccountResponse = from_binary(&res).unwrap();
+        assert_eq!(value.account.len(), 2);
+        assert_eq!(value.account, vec!["Account 1", "Account 2"]);
+    }
+
+    #[test]
+    fn check_add_unique_account_name() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // user 1 initializes the contract
+
+        let user = mock_info("user1", &[]);
+
+        // user 1 adds an account
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+
+        assert!(res.is_ok());
+
+        let msg = QueryMsg::GetAccount {
+            address: user.sender.to_owned().to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: AccountResponse = from_binary(&res).unwrap();
+        assert_eq!(value.account.len(), 1);
+        assert_eq!(value.account, vec!["Account 1"]);
+
+        // user 1 add same account name again
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn should_be_able_to_deposit() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // user 1 initializes the contract
+
+        let user = mock_info("user1", &[]);
+
+        // user 1 adds an account
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+
+        // user 1 deposits some money
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(55),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 1"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(55));
+    }
+
+    #[test]
+    fn should_check_permission_before_deposit() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // user 1 initializes the contract
+
+        let user = mock_info("user1", &[]);
+
+        // user 1 adds an account
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+
+        // user 1 deposits some money
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(55),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info("fake_contract", &[]);
+        let res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+
+        assert!(res.is_err());
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 1"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(0));
+    }
+
+    #[test]
+    fn should_be_able_to_withdraw() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // user 1 initializes the contract
+
+        let user = mock_info("user1", &[]);
+
+        // user 1 adds an account
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+
+        // user 1 deposits some money
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(55),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 1"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(55));
+
+        // user 1 withdraws some money
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(16),
+            token: None,
+            recipient: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+        println!("{:?}", _res);
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 1"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(39));
+    }
+
+    #[test]
+    fn should_collect_transfer_fee_in_treasury() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // user 1 and user 2 each add an account
+
+        let user1 = mock_info("user1", &[]);
+        let user2 = mock_info("user2", &[]);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 2"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user2.clone(), msg);
+
+        // user 1 deposits some money
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(500),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+
+        // user 1 transfers to user 2, paying a 1% fee
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Account 2"),
+            amount: Uint128::new(300),
+            token: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTreasury { token: None },
+        )
+        .unwrap();
+        let value: TreasuryResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(3));
+
+        // only the owner can withdraw fees
+        let msg = ExecuteMsg::WithdrawFees {
+            recipient: String::from("user1"),
+            amount: Uint128::new(3),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        // cannot withdraw more than was collected
+        let msg = ExecuteMsg::WithdrawFees {
+            recipient: String::from("creator"),
+            amount: Uint128::new(4),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), msg);
+        assert!(res.is_err());
+
+        let msg = ExecuteMsg::WithdrawFees {
+            recipient: String::from("creator"),
+            amount: Uint128::new(2),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(1, res.messages.len());
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTreasury { token: None },
+        )
+        .unwrap();
+        let value: TreasuryResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(1));
+    }
+
+    #[test]
+    fn should_apply_configured_fee_schedule() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: Some(FeeConfig {
+                rate: Decimal::percent(2),
+                min: Some(Uint128::new(5)),
+                max: Some(Uint128::new(10)),
+                bands: vec![],
+            }),
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user1 = mock_info("user1", &[]);
+        let user2 = mock_info("user2", &[]);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 2"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user2.clone(), msg);
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(2000),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+
+        // 2% of 100 is below the minimum fee
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Account 2"),
+            amount: Uint128::new(100),
+            token: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 2"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(95));
+
+        // 2% of 1000 is above the maximum fee
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Account 2"),
+            amount: Uint128::new(1000),
+            token: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTreasury { token: None },
+        )
+        .unwrap();
+        let value: TreasuryResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(15));
+
+        // only the owner can change the schedule
+        let fee = FeeConfig {
+            rate: Decimal::zero(),
+            min: None,
+            max: None,
+            bands: vec![],
+        };
+        let msg = ExecuteMsg::UpdateFeeConfig { fee: fee.clone() };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        // caps must not contradict each other
+        let msg = ExecuteMsg::UpdateFeeConfig {
+            fee: FeeConfig {
+                rate: Decimal::percent(1),
+                min: Some(Uint128::new(10)),
+                max: Some(Uint128::new(5)),
+                bands: vec![],
+            },
+        };
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), msg);
+        assert!(res.is_err());
+
+        let msg = ExecuteMsg::UpdateFeeConfig { fee: fee.clone() };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(value.fee, fee);
+    }
+
+    #[test]
+    fn should_resolve_fee_tiers_and_bands() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: Some(FeeConfig {
+                rate: Decimal::percent(2),
+                min: Some(Uint128::new(5)),
+                max: None,
+                bands: vec![FeeBand {
+                    from: Uint128::new(1000),
+                    rate: Decimal::percent(1),
+                }],
+            }),
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user1 = mock_info("user1", &[]);
+        let merchant = mock_info("merchant", &[]);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Shop"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), merchant.clone(), msg);
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(5000),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+
+        // large transfers fall into the cheaper band
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Shop"),
+            amount: Uint128::new(2000),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+        assert_eq!(res.attributes[5].value, "20");
+        assert_eq!(res.attributes[6].value, "band_1000");
+
+        // only the owner can grant tiers
+        let msg = ExecuteMsg::SetOwnerFeeTier {
+            owner: String::from("merchant"),
+            tier: Some(FeeTier {
+                name: String::from("merchant"),
+                rate: Decimal::zero(),
+            }),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        // paying a merchant is exempt, even from the minimum fee
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Shop"),
+            amount: Uint128::new(100),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+        assert_eq!(res.attributes[5].value, "0");
+        assert_eq!(res.attributes[6].value, "merchant");
+
+        // removing the tier restores the standard rate
+        let msg = ExecuteMsg::SetOwnerFeeTier {
+            owner: String::from("merchant"),
+            tier: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Shop"),
+            amount: Uint128::new(100),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+        assert_eq!(res.attributes[5].value, "5");
+        assert_eq!(res.attributes[6].value, "standard");
+    }
+
+    #[test]
+    fn should_refuse_removing_token_with_outstanding_balances() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user1 = mock_info("user1", &[]);
+        let user2 = mock_info("user2", &[]);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 2"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user2.clone(), msg);
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Account 2"),
+            amount: Uint128::new(100),
+            token: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        // balances in the old currency survive a currency change
+        let msg = ExecuteMsg::ChangeCurrency {
+            currency: String::from("new_currency"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let remove = ExecuteMsg::RemoveToken {
+            token: String::from(MOCK_CONTRACT_ADDR),
+        };
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), remove.clone());
+        match res {
+            Err(ContractError::OutstandingBalances { total }) => {
+                assert_eq!(total, Uint128::new(99))
+            }
+            _ => panic!("Must return outstanding balances error"),
+        }
+
+        // drain the account that received the transfer
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 2"),
+            amount: Uint128::new(99),
+            token: Some(String::from(MOCK_CONTRACT_ADDR)),
+            recipient: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user2.clone(), msg).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), remove.clone());
+        match res {
+            Err(ContractError::OutstandingFees { amount }) => assert_eq!(amount, Uint128::new(1)),
+            _ => panic!("Must return outstanding fees error"),
+        }
+
+        let msg = ExecuteMsg::WithdrawFees {
+            recipient: String::from("creator"),
+            amount: Uint128::new(1),
+            token: Some(String::from(MOCK_CONTRACT_ADDR)),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), remove).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetTokens {}).unwrap();
+        let value: TokensResponse = from_binary(&res).unwrap();
+        assert_eq!(value.tokens, vec![Addr::unchecked("new_currency")]);
+    }
+
+    #[test]
+    fn should_hold_several_tokens_per_account() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user = mock_info("user1", &[]);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+
+        let deposit = |amount: u128| {
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: String::from("user1"),
+                amount: Uint128::new(amount),
+                msg: to_binary(&ReceiveMsg::Deposit {
+                    account: String::from("Account 1"),
+                })
+                .unwrap(),
+            })
+        };
+
+        // other tokens are rejected until the owner accepts them
+        let other = mock_info("other_token", &[]);
+        let res = execute(deps.as_mut(), mock_env(), other.clone(), deposit(20));
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let msg = ExecuteMsg::AddToken {
+            token: String::from("other_token"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), deposit(55)).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), other.clone(), deposit(20)).unwrap();
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 1"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(55));
+        assert_eq!(
+            value.holdings,
+            vec![
+                TokenBalance {
+                    token: String::from(MOCK_CONTRACT_ADDR),
+                    amount: Uint128::new(55),
+                },
+                TokenBalance {
+                    token: String::from("other_token"),
+                    amount: Uint128::new(20),
+                },
+            ]
+        );
+
+        // withdrawing pays out through the token's own contract
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(15),
+            token: Some(String::from("other_token")),
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            Cw20Contract(Addr::unchecked("other_token"))
+                .call(Cw20ExecuteMsg::Transfer {
+                    recipient: String::from("user1"),
+                    amount: Uint128::new(15),
+                })
+                .unwrap()
+        );
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 1"),
+            token: Some(String::from("other_token")),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(5));
+    }
+
+    #[test]
+    fn should_bank_native_coins() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user = mock_info("user1", &[]);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+
+        let deposit = ExecuteMsg::DepositNative {
+            account: String::from("Account 1"),
+        };
+
+        let res = execute(deps.as_mut(), mock_env(), user.clone(), deposit.clone());
+        match res {
+            Err(ContractError::ZeroAmount {}) => {}
+            _ => panic!("Must return zero amount error"),
+        }
+
+        // uluna is not accepted yet
+        let funded = mock_info("user1", &coins(100, "uluna"));
+        let res = execute(deps.as_mut(), mock_env(), funded.clone(), deposit.clone());
+        match res {
+            Err(ContractError::UnsupportedToken { token }) => assert_eq!(token, "uluna"),
+            _ => panic!("Must return unsupported token error"),
+        }
+
+        let msg = ExecuteMsg::AddNativeToken {
+            denom: String::from("uluna"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let _res = execute(deps.as_mut(), mock_env(), funded, deposit).unwrap();
+
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(40),
+            token: Some(String::from("uluna")),
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: String::from("user1"),
+                amount: coins(40, "uluna"),
+            })
+        );
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 1"),
+            token: Some(String::from("uluna")),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(60));
+    }
+
+    #[test]
+    fn should_migrate_legacy_balances() {
+        let mut deps = mock_dependencies();
+
+        // storage as written by 0.1.0
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.1.0").unwrap();
+        // `State` of 0.1.0 had a mandatory owner and nothing but the currency
+        let state = format!(
+            r#"{{"owner":"creator","currency":"{}"}}"#,
+            MOCK_CONTRACT_ADDR
+        );
+        deps.as_mut().storage.set(b"state", state.as_bytes());
+        let legacy = [("Account 1", "user1", 55), ("Account 2", "user2", 0)];
+        for (name, address, value) in legacy.iter() {
+            LEGACY_BALANCES
+                .save(
+                    deps.as_mut().storage,
+                    name.to_string(),
+                    &LegacyBalanceData {
+                        address: Addr::unchecked(*address),
+                        value: Uint128::new(*value),
+                    },
+                )
+                .unwrap();
+        }
+        LEGACY_OWNER
+            .save(
+                deps.as_mut().storage,
+                &Addr::unchecked("user1"),
+                &vec![String::from("Account 1")],
+            )
+            .unwrap();
+
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(res.attributes[3].value, "0.2.0,0.3.0");
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(value.owner, Some(Addr::unchecked("creator")));
+        assert_eq!(value.currency, Addr::unchecked(MOCK_CONTRACT_ADDR));
+        assert_eq!(value.fee, FeeConfig::default());
+
+        // the owner index replaces the legacy owner lists
+        let msg = QueryMsg::GetAccount {
+            address: String::from("user1"),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: AccountResponse = from_binary(&res).unwrap();
+        assert_eq!(value.account, vec!["Account 1"]);
+        assert!(LEGACY_OWNER
+            .may_load(deps.as_ref().storage, &Addr::unchecked("user1"))
+            .unwrap()
+            .is_none());
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 1"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(55));
+
+        // the migrated account keeps its owner
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(55),
+            token: None,
+            recipient: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("user2", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("user1", &[]), msg).unwrap();
+
+        assert!(LEGACY_BALANCES
+            .may_load(deps.as_ref().storage, String::from("Account 1"))
+            .unwrap()
+            .is_none());
+        let version = get_contract_version(deps.as_ref().storage).unwrap();
+        assert_eq!(version.version, CONTRACT_VERSION);
+    }
+
+    #[test]
+    fn should_refuse_foreign_or_newer_contract_migration() {
+        let mut deps = mock_dependencies();
+
+        set_contract_version(deps.as_mut().storage, "crates.io:cw20-base", "0.1.0").unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+        match res {
+            Err(ContractError::CannotMigrate { previous_contract }) => {
+                assert_eq!(previous_contract, "crates.io:cw20-base")
+            }
+            _ => panic!("Must return cannot migrate error"),
+        }
+
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "99.0.0").unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+        match res {
+            Err(ContractError::CannotDowngrade { .. }) => {}
+            _ => panic!("Must return cannot downgrade error"),
+        }
+    }
+
+    #[test]
+    fn should_transfer_ownership_in_two_steps() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let admin = mock_info("admin", &[]);
+
+        let env = mock_env();
+        let msg = ExecuteMsg::ProposeOwner {
+            new_owner: String::from("admin"),
+            expiry: Some(Expiration::AtHeight(env.block.height + 10)),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), creator.clone(), msg).unwrap();
+
+        // nobody else can take the offer
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("user1", &[]),
+            ExecuteMsg::AcceptOwnership {},
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        // the offer lapses after its expiry
+        let mut late = env.clone();
+        late.block.height += 10;
+        let res = execute(
+            deps.as_mut(),
+            late,
+            admin.clone(),
+            ExecuteMsg::AcceptOwnership {},
+        );
+        match res {
+            Err(ContractError::OwnershipProposalExpired {}) => {}
+            _ => panic!("Must return proposal expired error"),
+        }
+
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            admin.clone(),
+            ExecuteMsg::AcceptOwnership {},
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOwnership {}).unwrap();
+        let value: OwnershipResponse = from_binary(&res).unwrap();
+        assert_eq!(value.owner, Some(Addr::unchecked("admin")));
+        assert_eq!(value.pending_owner, None);
+
+        // the previous owner lost its rights
+        let msg = ExecuteMsg::AddNativeToken {
+            denom: String::from("uluna"),
+        };
+        let res = execute(deps.as_mut(), env.clone(), creator.clone(), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            admin.clone(),
+            ExecuteMsg::RenounceOwnership {},
+        )
+        .unwrap();
+
+        let res = execute(deps.as_mut(), env, admin.clone(), msg);
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+    }
+
+    #[test]
+    fn should_withdraw_to_recipient_and_contract() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user = mock_info("user1", &[]);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+
+        // pay a merchant directly
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(30),
+            token: None,
+            recipient: Some(String::from("merchant")),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            Cw20Contract(Addr::unchecked(MOCK_CONTRACT_ADDR))
+                .call(Cw20ExecuteMsg::Transfer {
+                    recipient: String::from("merchant"),
+                    amount: Uint128::new(30),
+                })
+                .unwrap()
+        );
+
+        // stake through the cw20 receive hook of another contract
+        let hook = to_binary(&ReceiveMsg::Deposit {
+            account: String::from("Savings"),
+        })
+        .unwrap();
+        let msg = ExecuteMsg::WithdrawAndSend {
+            account: String::from("Account 1"),
+            amount: Uint128::new(50),
+            token: None,
+            contract: String::from("staking"),
+            msg: hook.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            Cw20Contract(Addr::unchecked(MOCK_CONTRACT_ADDR))
+                .call(Cw20ExecuteMsg::Send {
+                    contract: String::from("staking"),
+                    amount: Uint128::new(50),
+                    msg: hook,
+                })
+                .unwrap()
+        );
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 1"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(20));
+    }
+
+    #[test]
+    fn should_list_accounts_page_by_page() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user1 = mock_info("user1", &[]);
+        let user2 = mock_info("user2", &[]);
+
+        for (user, name) in [(&user1, "B"), (&user2, "A"), (&user1, "C")].iter() {
+            let msg = ExecuteMsg::CreateAccount {
+                account_name: name.to_string(),
+                lock_until: None,
+                penalty: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), (*user).clone(), msg).unwrap();
+        }
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(7),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("A"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+
+        let msg = QueryMsg::ListAccounts {
+            start_after: None,
+            limit: Some(2),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ListAccountsResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value.accounts,
+            vec![
+                AccountInfo {
+                    name: String::from("A"),
+                    owner: Addr::unchecked("user2"),
+                    pending_owner: None,
+                    balances: vec![TokenBalance {
+                        token: String::from(MOCK_CONTRACT_ADDR),
+                        amount: Uint128::new(7),
+                    }],
+                },
+                AccountInfo {
+                    name: String::from("B"),
+                    owner: Addr::unchecked("user1"),
+                    pending_owner: None,
+                    balances: vec![],
+                },
+            ]
+        );
+
+        let msg = QueryMsg::ListAccounts {
+            start_after: Some(String::from("B")),
+            limit: Some(2),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ListAccountsResponse = from_binary(&res).unwrap();
+        assert_eq!(value.accounts.len(), 1);
+        assert_eq!(value.accounts[0].name, "C");
+
+        let msg = QueryMsg::ListAccountsByOwner {
+            owner: String::from("user1"),
+            start_after: Some(String::from("B")),
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ListAccountsResponse = from_binary(&res).unwrap();
+        assert_eq!(value.accounts.len(), 1);
+        assert_eq!(value.accounts[0].name, "C");
+    }
+
+    #[test]
+    fn should_rebuild_owner_index_on_migration() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+        let creator = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator, msg).unwrap();
+
+        // accounts as written by 0.2.0, without the owner index
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.2.0").unwrap();
+        let unindexed: Map<&str, AccountData> = Map::new("account");
+        unindexed
+            .save(
+                deps.as_mut().storage,
+                "Account 1",
+                &AccountData {
+                    address: Addr::unchecked("user1"),
+                    kind: AccountKind::Standard,
+                },
+            )
+            .unwrap();
+
+        let msg = QueryMsg::GetAccount {
+            address: String::from("user1"),
+        };
+        assert!(query(deps.as_ref(), mock_env(), msg.clone()).is_err());
+
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(res.attributes[3].value, "0.3.0");
+
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: AccountResponse = from_binary(&res).unwrap();
+        assert_eq!(value.account, vec!["Account 1"]);
+    }
+
+    #[test]
+    fn should_close_account_and_sweep_balance() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user1 = mock_info("user1", &[]);
+        let user2 = mock_info("user2", &[]);
+
+        for (user, name) in [
+            (&user1, "Account 1"),
+            (&user1, "Account 2"),
+            (&user2, "Account 3"),
+        ]
+        .iter()
+        {
+            let msg = ExecuteMsg::CreateAccount {
+                account_name: name.to_string(),
+                lock_until: None,
+                penalty: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), (*user).clone(), msg).unwrap();
+        }
+
+        for name in ["Account 1", "Account 2"].iter() {
+            let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: String::from(MOCK_CONTRACT_ADDR),
+                amount: Uint128::new(200),
+                msg: to_binary(&ReceiveMsg::Deposit {
+                    account: name.to_string(),
+                })
+                .unwrap(),
+            });
+            let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+            let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
+        }
+
+        // only the owner can close an account
+        let msg = ExecuteMsg::CloseAccount {
+            account: String::from("Account 1"),
+            sweep_to: Some(String::from("Account 3")),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user2.clone(), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        // sweeping into another owner's account pays the fee
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 3"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(198));
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 1"),
+            token: None,
+        };
+        assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+
+        // without a sweep target the balance is paid out
+        let msg = ExecuteMsg::CloseAccount {
+            account: String::from("Account 2"),
+            sweep_to: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+        assert_eq!(1, res.messages.len());
+
+        let msg = QueryMsg::GetAccount {
+            address: String::from("user1"),
+        };
+        assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+
+        // the name is free again
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user2.clone(), msg).unwrap();
+    }
+
+    #[test]
+    fn should_transfer_account_ownership() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user1 = mock_info("user1", &[]);
+        let user2 = mock_info("user2", &[]);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        // only the account owner can offer it
+        let msg = ExecuteMsg::TransferAccountOwnership {
+            account: String::from("Account 1"),
+            new_owner: String::from("user2"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user2.clone(), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        // nobody else can take the offer
+        let msg = ExecuteMsg::AcceptAccountOwnership {
+            account: String::from("Account 1"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let msg = QueryMsg::ListAccounts {
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ListAccountsResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value.accounts[0].pending_owner,
+            Some(Addr::unchecked("user2"))
+        );
+
+        let msg = ExecuteMsg::AcceptAccountOwnership {
+            account: String::from("Account 1"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user2.clone(), msg.clone()).unwrap();
+
+        let msg = QueryMsg::GetAccount {
+            address: String::from("user2"),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: AccountResponse = from_binary(&res).unwrap();
+        assert_eq!(value.account, vec![String::from("Account 1")]);
+
+        let msg = QueryMsg::GetAccount {
+            address: String::from("user1"),
+        };
+        assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+
+        // the offer is used up
+        let msg = ExecuteMsg::AcceptAccountOwnership {
+            account: String::from("Account 1"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user2.clone(), msg);
+        match res {
+            Err(ContractError::NoPendingOwner {}) => {}
+            _ => panic!("Must return no pending owner error"),
+        }
+
+        // the previous owner lost control
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::zero(),
+            token: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        // an offer can be withdrawn
+        let msg = ExecuteMsg::TransferAccountOwnership {
+            account: String::from("Account 1"),
+            new_owner: String::from("user1"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user2.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CancelAccountOwnershipTransfer {
+            account: String::from("Account 1"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user2.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::AcceptAccountOwnership {
+            account: String::from("Account 1"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+        match res {
+            Err(ContractError::NoPendingOwner {}) => {}
+            _ => panic!("Must return no pending owner error"),
+        }
+    }
+
+    #[test]
+    fn should_spend_through_allowance() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user1 = mock_info("user1", &[]);
+        let user2 = mock_info("user2", &[]);
+        let bot = mock_info("bot", &[]);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 2"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user2.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(1000),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
+
+        // nothing can be spent without an allowance
+        let msg = ExecuteMsg::WithdrawFrom {
+            account: String::from("Account 1"),
+            amount: Uint128::new(100),
+            token: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), bot.clone(), msg);
+        match res {
+            Err(ContractError::NoAllowance {}) => {}
+            _ => panic!("Must return no allowance error"),
+        }
+
+        // only the account owner grants allowances
+        let msg = ExecuteMsg::IncreaseAllowance {
+            account: String::from("Account 1"),
+            spender: String::from("bot"),
+            amount: Uint128::new(300),
+            token: None,
+            expires: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user2.clone(), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::DecreaseAllowance {
+            account: String::from("Account 1"),
+            spender: String::from("bot"),
+            amount: Uint128::new(50),
+            token: None,
+            expires: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::WithdrawFrom {
+            account: String::from("Account 1"),
+            amount: Uint128::new(100),
+            token: None,
+            recipient: Some(String::from("user2")),
+        };
+        let res = execute(deps.as_mut(), mock_env(), bot.clone(), msg).unwrap();
+        assert_eq!(1, res.messages.len());
+
+        let msg = ExecuteMsg::TransferFrom {
+            from: String::from("Account 1"),
+            to: String::from("Account 2"),
+            amount: Uint128::new(100),
+            token: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), bot.clone(), msg).unwrap();
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Account 2"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(99));
+
+        let msg = QueryMsg::Allowance {
+            account: String::from("Account 1"),
+            spender: String::from("bot"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: AllowanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.allowance, Uint128::new(50));
+
+        // the allowance caps what the spender can move
+        let msg = ExecuteMsg::TransferFrom {
+            from: String::from("Account 1"),
+            to: String::from("Account 2"),
+            amount: Uint128::new(51),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), bot.clone(), msg);
+        match res {
+            Err(ContractError::InsufficientAllowance {
+                allowance,
+                required,
+            }) => {
+                assert_eq!(allowance, Uint128::new(50));
+                assert_eq!(required, Uint128::new(51));
+            }
+            _ => panic!("Must return insufficient allowance error"),
+        }
+
+        let msg = ExecuteMsg::IncreaseAllowance {
+            account: String::from("Account 1"),
+            spender: String::from("payroll"),
+            amount: Uint128::new(10),
+            token: None,
+            expires: Some(Expiration::AtHeight(mock_env().block.height + 1)),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = QueryMsg::AllAllowances {
+            account: String::from("Account 1"),
+            token: None,
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: AllAllowancesResponse = from_binary(&res).unwrap();
+        assert_eq!(value.allowances.len(), 2);
+        assert_eq!(value.allowances[0].spender, "bot");
+
+        let msg = QueryMsg::AllAllowances {
+            account: String::from("Account 1"),
+            token: None,
+            start_after: Some(String::from("bot")),
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: AllAllowancesResponse = from_binary(&res).unwrap();
+        assert_eq!(value.allowances.len(), 1);
+        assert_eq!(value.allowances[0].spender, "payroll");
+
+        // expired allowances cannot be spent
+        let mut env = mock_env();
+        env.block.height += 1;
+        let msg = ExecuteMsg::WithdrawFrom {
+            account: String::from("Account 1"),
+            amount: Uint128::new(10),
+            token: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), env, mock_info("payroll", &[]), msg);
+        match res {
+            Err(ContractError::Expired {}) => {}
+            _ => panic!("Must return expired error"),
+        }
+    }
+
+    #[test]
+    fn should_require_approvals_on_joint_account() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let alice = mock_info("alice", &[]);
+        let bob = mock_info("bob", &[]);
+        let carol = mock_info("carol", &[]);
+
+        let msg = ExecuteMsg::CreateJointAccount {
+            account_name: String::from("Treasury"),
+            signers: vec![
+                String::from("alice"),
+                String::from("bob"),
+                String::from("carol"),
+            ],
+            threshold: 4,
+            limit: Uint128::new(100),
+            voting_period: 3600,
+        };
+        let res = execute(deps.as_mut(), mock_env(), alice.clone(), msg);
+        match res {
+            Err(ContractError::InvalidThreshold { .. }) => {}
+            _ => panic!("Must return invalid threshold error"),
+        }
+
+        let msg = ExecuteMsg::CreateJointAccount {
+            account_name: String::from("Treasury"),
+            signers: vec![
+                String::from("alice"),
+                String::from("bob"),
+                String::from("carol"),
+            ],
+            threshold: 2,
+            limit: Uint128::new(100),
+            voting_period: 3600,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), alice.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Payee"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(1000),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Treasury"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
+
+        // any signer spends within the limit
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Treasury"),
+            amount: Uint128::new(100),
+            token: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), bob.clone(), msg.clone()).unwrap();
+        assert_eq!(1, res.messages.len());
+
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), msg);
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        // above the limit a proposal is opened instead
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Treasury"),
+            to: String::from("Payee"),
+            amount: Uint128::new(500),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), alice.clone(), msg).unwrap();
+        assert_eq!(res.attributes[3], ("proposal_id", "1"));
+
+        let msg = QueryMsg::GetProposal { proposal_id: 1 };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ProposalResponse = from_binary(&res).unwrap();
+        assert_eq!(value.approvals, vec![Addr::unchecked("alice")]);
+
+        let msg = ExecuteMsg::Approve { proposal_id: 1 };
+        let res = execute(deps.as_mut(), mock_env(), alice.clone(), msg.clone());
+        match res {
+            Err(ContractError::AlreadyApproved {}) => {}
+            _ => panic!("Must return already approved error"),
+        }
+
+        let _res = execute(deps.as_mut(), mock_env(), carol.clone(), msg.clone()).unwrap();
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Payee"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(495));
+
+        let msg = QueryMsg::ListProposals {
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ListProposalsResponse = from_binary(&res).unwrap();
+        assert!(value.proposals.is_empty());
+
+        // the creator holds no special power over a joint account
+        let msg = ExecuteMsg::CloseAccount {
+            account: String::from("Treasury"),
+            sweep_to: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), alice.clone(), msg);
+        match res {
+            Err(ContractError::JointAccount { .. }) => {}
+            _ => panic!("Must return joint account error"),
+        }
+    }
+
+    #[test]
+    fn should_check_roles_on_privileged_messages() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let manager = mock_info("manager", &[]);
+
+        let update = ExecuteMsg::UpdateFeeConfig {
+            fee: FeeConfig {
+                rate: Decimal::percent(2),
+                min: None,
+                max: None,
+                bands: vec![],
+            },
+        };
+        let res = execute(deps.as_mut(), mock_env(), manager.clone(), update.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        // only the owner hands out roles
+        let msg = ExecuteMsg::GrantRole {
+            role: Role::FeeManager,
+            address: String::from("manager"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), manager.clone(), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let _res = execute(deps.as_mut(), mock_env(), manager.clone(), update).unwrap();
+
+        // a role grants nothing beyond its own messages
+        let msg = ExecuteMsg::AddNativeToken {
+            denom: String::from("uluna"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), manager.clone(), msg);
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let msg = QueryMsg::Roles { role: None };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: RolesResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value.grants,
+            vec![RoleGrant {
+                role: Role::FeeManager,
+                address: Addr::unchecked("manager"),
+            }]
+        );
+
+        let msg = ExecuteMsg::RevokeRole {
+            role: Role::FeeManager,
+            address: String::from("manager"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let msg = QueryMsg::Roles {
+            role: Some(Role::FeeManager),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: RolesResponse = from_binary(&res).unwrap();
+        assert!(value.grants.is_empty());
+    }
+
+    #[test]
+    fn should_refuse_paused_operations() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user1 = mock_info("user1", &[]);
+        let pauser = mock_info("pauser", &[]);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::GrantRole {
+            role: Role::Pauser,
+            address: String::from("pauser"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Pause {
+            operations: Some(vec![Operation::Withdrawals]),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let _res = execute(deps.as_mut(), mock_env(), pauser.clone(), msg).unwrap();
+
+        // deposits still go through
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg).unwrap();
+
+        let withdraw = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(10),
+            token: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), withdraw.clone());
+        match res {
+            Err(ContractError::Paused { operation }) => assert_eq!(operation, "withdrawals"),
+            _ => panic!("Must return paused error"),
+        }
+
+        let msg = ExecuteMsg::Pause { operations: None };
+        let _res = execute(deps.as_mut(), mock_env(), pauser.clone(), msg).unwrap();
+
+        let msg = QueryMsg::GetPauseState {};
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: PauseState = from_binary(&res).unwrap();
+        assert!(value.deposits && value.withdrawals && value.transfers && value.account_creation);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 2"),
+            lock_until: None,
+            penalty: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+        match res {
+            Err(ContractError::Paused { operation }) => assert_eq!(operation, "account_creation"),
+            _ => panic!("Must return paused error"),
+        }
+
+        let msg = ExecuteMsg::Unpause { operations: None };
+        let _res = execute(deps.as_mut(), mock_env(), pauser.clone(), msg).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), withdraw).unwrap();
+        assert_eq!(1, res.messages.len());
+    }
+
+    #[test]
+    fn should_block_outflows_of_frozen_account() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
 
-        // user 1 initializes the contract
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user1 = mock_info("user1", &[]);
+
+        for name in ["Account 1", "Account 2"].iter() {
+            let msg = ExecuteMsg::CreateAccount {
+                account_name: name.to_string(),
+                lock_until: None,
+                penalty: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+        }
+
+        let msg = ExecuteMsg::FreezeAccount {
+            account: String::from("Account 1"),
+            reason: String::from("court order"),
+            expires: Some(Expiration::AtHeight(mock_env().block.height + 10)),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        // deposits still go through
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
+
+        let transfer = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Account 2"),
+            amount: Uint128::new(10),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), transfer.clone());
+        match res {
+            Err(ContractError::AccountFrozen { reason, .. }) => assert_eq!(reason, "court order"),
+            _ => panic!("Must return account frozen error"),
+        }
+
+        let msg = QueryMsg::ListFrozenAccounts {
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+        let value: FrozenAccountsResponse = from_binary(&res).unwrap();
+        assert_eq!(value.accounts.len(), 1);
+        assert_eq!(value.accounts[0].account, "Account 1");
+
+        // the hold lifts once it expires
+        let mut env = mock_env();
+        env.block.height += 10;
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: FrozenAccountsResponse = from_binary(&res).unwrap();
+        assert!(value.accounts.is_empty());
+
+        let _res = execute(deps.as_mut(), env, user1.clone(), transfer.clone()).unwrap();
+
+        let msg = ExecuteMsg::FreezeAccount {
+            account: String::from("Account 1"),
+            reason: String::from("review"),
+            expires: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::UnfreezeAccount {
+            account: String::from("Account 1"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), transfer).unwrap();
+    }
+
+    #[test]
+    fn should_enforce_withdrawal_rate_limits() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user1 = mock_info("user1", &[]);
+        let user2 = mock_info("user2", &[]);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 2"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user2.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(1000),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
+
+        let msg = ExecuteMsg::SetAccountRateLimit {
+            account: String::from("Account 1"),
+            token: None,
+            limit: Some(RateLimit {
+                amount: Uint128::new(300),
+                window: 86400,
+            }),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::SetGlobalRateLimit {
+            token: None,
+            limit: Some(RateLimit {
+                amount: Uint128::new(500),
+                window: 3600,
+            }),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(200),
+            token: None,
+            recipient: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        // transfers to other owners count against the same window
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Account 2"),
+            amount: Uint128::new(150),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+        match res {
+            Err(ContractError::RateLimitExceeded { remaining }) => {
+                assert_eq!(remaining, Uint128::new(100))
+            }
+            _ => panic!("Must return rate limit exceeded error"),
+        }
+
+        let msg = QueryMsg::GetRateLimit {
+            account: String::from("Account 1"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+        let value: RateLimitResponse = from_binary(&res).unwrap();
+        assert_eq!(value.remaining, Some(Uint128::new(100)));
+        assert_eq!(value.global_remaining, Some(Uint128::new(300)));
+
+        // a new window starts once the last one has passed
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: RateLimitResponse = from_binary(&res).unwrap();
+        assert_eq!(value.remaining, Some(Uint128::new(300)));
+
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(300),
+            token: None,
+            recipient: None,
+        };
+        let _res = execute(deps.as_mut(), env, user1.clone(), msg).unwrap();
+    }
+
+    #[test]
+    fn should_lock_savings_account() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let user1 = mock_info("user1", &[]);
+        let lock_until = mock_env().block.time.plus_seconds(1000);
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Savings"),
+            lock_until: Some(lock_until),
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Flexible"),
+            lock_until: Some(lock_until),
+            penalty: Some(Decimal::percent(10)),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Checking"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        for name in ["Savings", "Flexible"].iter() {
+            let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: String::from(MOCK_CONTRACT_ADDR),
+                amount: Uint128::new(100),
+                msg: to_binary(&ReceiveMsg::Deposit {
+                    account: name.to_string(),
+                })
+                .unwrap(),
+            });
+            let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+            let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
+        }
+
+        let msg = QueryMsg::GetBalance {
+            account: String::from("Savings"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: BalanceResponse = from_binary(&res).unwrap();
+        assert_eq!(value.locked_until, Some(lock_until));
+        assert!(value.locked);
+
+        // not even into another account of the same owner
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Savings"),
+            to: String::from("Checking"),
+            amount: Uint128::new(100),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg.clone());
+        match res {
+            Err(ContractError::AccountLocked { until }) => assert_eq!(until, lock_until),
+            _ => panic!("Must return account locked error"),
+        }
+
+        let mut env = mock_env();
+        env.block.time = lock_until;
+        let _res = execute(deps.as_mut(), env, user1.clone(), msg).unwrap();
+
+        // breaking the lock early costs the penalty
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Flexible"),
+            amount: Uint128::new(100),
+            token: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: String::from(MOCK_CONTRACT_ADDR),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: String::from("user1"),
+                    amount: Uint128::new(90),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTreasury { token: None },
+        )
+        .unwrap();
+        let value: TreasuryResponse = from_binary(&res).unwrap();
+        assert_eq!(value.balance, Uint128::new(10));
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Forfeit"),
+            lock_until: Some(lock_until),
+            penalty: Some(Decimal::one()),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+        match res {
+            Err(ContractError::InvalidRequest(msg)) => {
+                assert_eq!(msg, "Penalty must be below 100%")
+            }
+            _ => panic!("Must return invalid request error"),
+        }
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Forfeit"),
+            lock_until: Some(lock_until),
+            penalty: Some(Decimal::zero()),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+        match res {
+            Err(ContractError::InvalidRequest(msg)) => assert_eq!(msg, "Penalty must not be zero"),
+            _ => panic!("Must return invalid request error"),
+        }
+
+        // the minimum fee and the penalty together cannot take more than the amount
+        let msg = ExecuteMsg::UpdateFeeConfig {
+            fee: FeeConfig {
+                min: Some(Uint128::new(10)),
+                ..FeeConfig::default()
+            },
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator, msg).unwrap();
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Other"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("user2", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Flexible"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
+
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Flexible"),
+            to: String::from("Other"),
+            amount: Uint128::new(10),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1, msg);
+        match res {
+            Err(ContractError::ChargesExceedAmount { amount, charged }) => {
+                assert_eq!(amount, Uint128::new(10));
+                assert_eq!(charged, Uint128::new(11));
+            }
+            _ => panic!("Must return charges exceed amount error"),
+        }
+    }
+
+    #[test]
+    fn should_record_account_history() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let _res = instantiate(deps.as_mut(), mock_env(), creator, msg).unwrap();
+
+        let user1 = mock_info("user1", &[]);
+        let user2 = mock_info("user2", &[]);
+
+        for (user, name) in [(&user1, "Account 1"), (&user2, "Account 2")].iter() {
+            let msg = ExecuteMsg::CreateAccount {
+                account_name: name.to_string(),
+                lock_until: None,
+                penalty: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), (*user).clone(), msg).unwrap();
+        }
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from("user1"),
+            amount: Uint128::new(1000),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
+
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Account 2"),
+            amount: Uint128::new(100),
+            token: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(200),
+            token: None,
+            recipient: Some(String::from("merchant")),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1, msg).unwrap();
+
+        let msg = QueryMsg::GetHistory {
+            account: String::from("Account 1"),
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: HistoryResponse = from_binary(&res).unwrap();
+        let kinds: Vec<_> = value.entries.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EntryKind::Withdrawal,
+                EntryKind::TransferOut,
+                EntryKind::Deposit
+            ]
+        );
+        assert_eq!(value.entries[0].counterparty, "merchant");
+        assert_eq!(value.entries[1].amount, Uint128::new(100));
+        assert_eq!(value.entries[1].fee, Uint128::new(1));
+        assert_eq!(value.entries[2].counterparty, "user1");
+        assert_eq!(value.entries[2].height, mock_env().block.height);
+
+        let msg = QueryMsg::GetHistory {
+            account: String::from("Account 2"),
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: HistoryResponse = from_binary(&res).unwrap();
+        assert_eq!(value.entries.len(), 1);
+        assert_eq!(value.entries[0].kind, EntryKind::TransferIn);
+        assert_eq!(value.entries[0].counterparty, "Account 1");
+        assert_eq!(value.entries[0].amount, Uint128::new(99));
+
+        // continue after the newest entry
+        let msg = QueryMsg::GetHistory {
+            account: String::from("Account 1"),
+            start_after: Some(4),
+            limit: Some(1),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: HistoryResponse = from_binary(&res).unwrap();
+        assert_eq!(value.entries.len(), 1);
+        assert_eq!(value.entries[0].kind, EntryKind::TransferOut);
+    }
+
+    #[test]
+    fn should_refuse_unbalanced_postings() {
+        let mut storage = MockStorage::new();
+
+        let postings = [
+            Posting::Debit(Book::External, Uint128::new(100)),
+            Posting::Credit(Book::Account("Account 1"), Uint128::new(99)),
+        ];
+        match ledger::post(&mut storage, "token", &postings) {
+            Err(ContractError::Unbalanced { debits, credits }) => {
+                assert_eq!(debits, Uint128::new(100));
+                assert_eq!(credits, Uint128::new(99));
+            }
+            _ => panic!("Must return unbalanced error"),
+        }
+
+        let postings = [
+            Posting::Debit(Book::External, Uint128::new(100)),
+            Posting::Credit(Book::Account("Account 1"), Uint128::new(99)),
+            Posting::Credit(Book::Treasury, Uint128::new(1)),
+        ];
+        ledger::post(&mut storage, "token", &postings).unwrap();
+
+        let held = TOTAL_BALANCES.load(&storage, "token").unwrap()
+            + TREASURY.load(&storage, "token").unwrap();
+        assert_eq!(held, Uint128::new(100));
+
+        // accounts and the treasury never go below zero
+        let postings = [
+            Posting::Debit(Book::Treasury, Uint128::new(2)),
+            Posting::Credit(Book::External, Uint128::new(2)),
+        ];
+        match ledger::post(&mut storage, "token", &postings) {
+            Err(ContractError::InsufficientFunds {
+                available,
+                required,
+            }) => {
+                assert_eq!(available, Uint128::new(1));
+                assert_eq!(required, Uint128::new(2));
+            }
+            _ => panic!("Must return insufficient funds error"),
+        }
+    }
+
+    #[test]
+    fn should_report_and_reconcile_reserves() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let _res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::AddNativeToken {
+            denom: String::from("uluna"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let user = mock_info("user1", &[]);
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::DepositNative {
+            account: String::from("Account 1"),
+        };
+        let funded = mock_info("user1", &coins(100, "uluna"));
+        let _res = execute(deps.as_mut(), mock_env(), funded, msg).unwrap();
+
+        // 30 uluna were sent straight to the contract
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(130, "uluna"));
+
+        let msg = QueryMsg::Reserves {
+            token: Some(String::from("uluna")),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+        let value: ReservesResponse = from_binary(&res).unwrap();
+        assert_eq!(value.liabilities, Uint128::new(100));
+        assert_eq!(value.assets, Uint128::new(130));
+        assert_eq!(value.surplus, Uint128::new(30));
+        assert!(value.solvent);
+
+        let reconcile = ExecuteMsg::ReconcileSurplus {
+            token: Some(String::from("uluna")),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user, reconcile.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator.clone(),
+            reconcile.clone(),
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ReservesResponse = from_binary(&res).unwrap();
+        assert_eq!(value.treasury, Uint128::new(30));
+        assert_eq!(value.surplus, Uint128::zero());
+
+        let res = execute(deps.as_mut(), mock_env(), creator, reconcile);
+        match res {
+            Err(ContractError::InvalidRequest(msg)) => assert_eq!(msg, "No surplus to reconcile"),
+            _ => panic!("Must return invalid request error"),
+        }
+    }
+
+    #[test]
+    fn should_recover_only_unowed_tokens() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let _res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::AddNativeToken {
+            denom: String::from("uluna"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
 
         let user = mock_info("user1", &[]);
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::DepositNative {
+            account: String::from("Account 1"),
+        };
+        let funded = mock_info("user1", &coins(100, "uluna"));
+        let _res = execute(deps.as_mut(), mock_env(), funded, msg).unwrap();
 
-        // user 1 adds an account
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(130, "uluna"));
 
-        let msg = ExecuteMsg::CreateAccount {
-            account_name: String::from("Account 1"),
+        let msg = ExecuteMsg::RecoverTokens {
+            token: Denom::Native(String::from("uluna")),
+            amount: Uint128::new(31),
+            recipient: String::from("user2"),
         };
-        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+        let res = execute(deps.as_mut(), mock_env(), user.clone(), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
 
-        let msg = QueryMsg::GetAccount {
-            address: user.sender.to_owned().to_string(),
+        let zero = ExecuteMsg::RecoverTokens {
+            token: Denom::Native(String::from("uluna")),
+            amount: Uint128::zero(),
+            recipient: String::from("user2"),
         };
-        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let value: AccountResponse = from_binary(&res).unwrap();
-        assert_eq!(value.account.len(), 1);
-        assert_eq!(value.account, vec!["Account 1"]);
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), zero);
+        match res {
+            Err(ContractError::ZeroAmount {}) => {}
+            _ => panic!("Must return zero amount error"),
+        }
 
-        // user 1 add more accounts
+        // customer funds stay in the bank
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), msg);
+        match res {
+            Err(ContractError::ExceedsSurplus { surplus }) => {
+                assert_eq!(surplus, Uint128::new(30))
+            }
+            _ => panic!("Must return exceeds surplus error"),
+        }
 
-        let msg = ExecuteMsg::CreateAccount {
-            account_name: String::from("Account 2"),
+        let msg = ExecuteMsg::RecoverTokens {
+            token: Denom::Native(String::from("uluna")),
+            amount: Uint128::new(30),
+            recipient: String::from("user2"),
         };
-        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: String::from("user2"),
+                amount: coins(30, "uluna"),
+            })
+        );
 
-        let msg = QueryMsg::GetAccount {
-            address: user.sender.to_owned().to_string(),
+        // tokens the bank does not accept are returned in full
+        let msg = ExecuteMsg::RecoverTokens {
+            token: Denom::Cw20(Addr::unchecked("stray")),
+            amount: Uint128::new(500),
+            recipient: String::from("user2"),
         };
-        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let value: AccountResponse = from_binary(&res).unwrap();
-        assert_eq!(value.account.len(), 2);
-        assert_eq!(value.account, vec!["Account 1", "Account 2"]);
+        let res = execute(deps.as_mut(), mock_env(), creator, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: String::from("stray"),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: String::from("user2"),
+                    amount: Uint128::new(500),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
     }
 
     #[test]
-    fn check_add_unique_account_name() {
+    fn should_return_typed_balance_errors() {
         let mut deps = mock_dependencies();
 
         let msg = InstantiateMsg {
             currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
         };
 
         let creator = mock_info("creator", &[]);
 
-        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
-        assert_eq!(0, res.messages.len());
-
-        // user 1 initializes the contract
+        let _res = instantiate(deps.as_mut(), mock_env(), creator, msg).unwrap();
 
         let user = mock_info("user1", &[]);
+        let create = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user.clone(), create.clone()).unwrap();
 
-        // user 1 adds an account
+        let res = execute(deps.as_mut(), mock_env(), user.clone(), create);
+        match res {
+            Err(ContractError::AccountExists { name }) => assert_eq!(name, "Account 1"),
+            _ => panic!("Must return account exists error"),
+        }
 
-        let msg = ExecuteMsg::CreateAccount {
-            account_name: String::from("Account 1"),
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Account 9"),
+            amount: Uint128::new(10),
+            token: None,
         };
         let res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+        match res {
+            Err(ContractError::AccountNotFound { name }) => assert_eq!(name, "Account 9"),
+            _ => panic!("Must return account not found error"),
+        }
 
-        assert_eq!(res.is_ok(), true);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from("user1"),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
 
-        let msg = QueryMsg::GetAccount {
-            address: user.sender.to_owned().to_string(),
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(150),
+            token: None,
+            recipient: None,
         };
-        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let value: AccountResponse = from_binary(&res).unwrap();
-        assert_eq!(value.account.len(), 1);
-        assert_eq!(value.account, vec!["Account 1"]);
+        let res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+        match res {
+            Err(ContractError::InsufficientFunds {
+                available,
+                required,
+            }) => {
+                assert_eq!(available, Uint128::new(100));
+                assert_eq!(required, Uint128::new(150));
+            }
+            _ => panic!("Must return insufficient funds error"),
+        }
 
-        // user 1 add same account name again
-        let msg = ExecuteMsg::CreateAccount {
-            account_name: String::from("Account 1"),
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::zero(),
+            token: None,
+            recipient: None,
         };
-        let res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
-        assert_eq!(res.is_err(), true);
+        let res = execute(deps.as_mut(), mock_env(), user, msg);
+        match res {
+            Err(ContractError::ZeroAmount {}) => {}
+            _ => panic!("Must return zero amount error"),
+        }
     }
 
     #[test]
-    fn should_be_able_to_deposit() {
+    fn should_expire_and_close_proposals() {
         let mut deps = mock_dependencies();
 
         let msg = InstantiateMsg {
             currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
         };
 
         let creator = mock_info("creator", &[]);
 
-        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
-        assert_eq!(0, res.messages.len());
-
-        // user 1 initializes the contract
+        let _res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
 
-        let user = mock_info("user1", &[]);
-
-        // user 1 adds an account
+        let alice = mock_info("alice", &[]);
+        let bob = mock_info("bob", &[]);
 
-        let msg = ExecuteMsg::CreateAccount {
-            account_name: String::from("Account 1"),
+        let msg = ExecuteMsg::CreateJointAccount {
+            account_name: String::from("Treasury"),
+            signers: vec![String::from("alice"), String::from("bob")],
+            threshold: 2,
+            limit: Uint128::new(100),
+            voting_period: 3600,
         };
-        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+        let _res = execute(deps.as_mut(), mock_env(), alice.clone(), msg).unwrap();
 
-        // user 1 deposits some money
         let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
             sender: String::from(MOCK_CONTRACT_ADDR),
-            amount: Uint128::new(55),
+            amount: Uint128::new(1000),
             msg: to_binary(&ReceiveMsg::Deposit {
-                account: String::from("Account 1"),
+                account: String::from("Treasury"),
             })
             .unwrap(),
         });
         let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
-        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+        let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
 
-        let msg = QueryMsg::GetBalance {
-            account: String::from("Account 1"),
+        // a transfer to a missing account is refused before it is proposed
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Treasury"),
+            to: String::from("Payee"),
+            amount: Uint128::new(500),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), alice.clone(), msg.clone());
+        match res {
+            Err(ContractError::AccountNotFound { name }) => assert_eq!(name, "Payee"),
+            _ => panic!("Must return account not found error"),
+        }
+
+        let create = ExecuteMsg::CreateAccount {
+            account_name: String::from("Payee"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator.clone(), create).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), alice.clone(), msg).unwrap();
+
+        let close = ExecuteMsg::CloseProposal { proposal_id: 1 };
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), close.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return unauthorized error"),
+        }
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3600);
+        let msg = ExecuteMsg::Approve { proposal_id: 1 };
+        let res = execute(deps.as_mut(), env.clone(), bob.clone(), msg);
+        match res {
+            Err(ContractError::ProposalExpired {}) => {}
+            _ => panic!("Must return proposal expired error"),
+        }
+
+        // anyone may clean up an expired proposal
+        let _res = execute(deps.as_mut(), env, creator, close).unwrap();
+
+        let msg = QueryMsg::ListProposals {
+            start_after: None,
+            limit: None,
         };
         let res = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let value: BalanceResponse = from_binary(&res).unwrap();
-        assert_eq!(value.balance, Uint128::new(55));
+        let value: ListProposalsResponse = from_binary(&res).unwrap();
+        assert!(value.proposals.is_empty());
+
+        // sending into a contract above the limit needs approval as well
+        let msg = ExecuteMsg::WithdrawAndSend {
+            account: String::from("Treasury"),
+            amount: Uint128::new(300),
+            token: None,
+            contract: String::from("staking"),
+            msg: Binary::from(b"{}".to_vec()),
+        };
+        let res = execute(deps.as_mut(), mock_env(), alice.clone(), msg).unwrap();
+        assert!(res.messages.is_empty());
+
+        let msg = QueryMsg::GetProposal { proposal_id: 2 };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ProposalResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value.expires,
+            Expiration::AtTime(mock_env().block.time.plus_seconds(3600))
+        );
+
+        let msg = ExecuteMsg::Approve { proposal_id: 2 };
+        let res = execute(deps.as_mut(), mock_env(), bob.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: String::from(MOCK_CONTRACT_ADDR),
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: String::from("staking"),
+                    amount: Uint128::new(300),
+                    msg: Binary::from(b"{}".to_vec()),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+
+        // signers can withdraw a proposal they no longer want
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Treasury"),
+            amount: Uint128::new(200),
+            token: None,
+            recipient: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), alice, msg).unwrap();
+        let close = ExecuteMsg::CloseProposal { proposal_id: 3 };
+        let _res = execute(deps.as_mut(), mock_env(), bob, close).unwrap();
+        assert!(PROPOSALS
+            .may_load(deps.as_ref().storage, 3)
+            .unwrap()
+            .is_none());
     }
 
     #[test]
-    fn should_check_permission_before_deposit() {
+    fn should_drop_rate_limits_of_closed_account() {
         let mut deps = mock_dependencies();
 
         let msg = InstantiateMsg {
             currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
         };
 
         let creator = mock_info("creator", &[]);
 
-        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        let _res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
 
-        // user 1 initializes the contract
+        let user1 = mock_info("user1", &[]);
+        let create = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), create.clone()).unwrap();
 
-        let user = mock_info("user1", &[]);
+        let msg = ExecuteMsg::SetAccountRateLimit {
+            account: String::from("Account 1"),
+            token: None,
+            limit: Some(RateLimit {
+                amount: Uint128::new(50),
+                window: 3600,
+            }),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator, msg).unwrap();
 
-        // user 1 adds an account
+        let deposit = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from("user1"),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), deposit.clone()).unwrap();
+
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(40),
+            token: None,
+            recipient: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CloseAccount {
+            account: String::from("Account 1"),
+            sweep_to: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user1.clone(), msg);
+        match res {
+            Err(ContractError::RateLimitExceeded { remaining }) => {
+                assert_eq!(remaining, Uint128::new(10))
+            }
+            _ => panic!("Must return rate limit exceeded error"),
+        }
 
+        // an account of the same owner takes the rest without counting as an outflow
         let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Account 2"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), msg).unwrap();
+        let msg = ExecuteMsg::CloseAccount {
+            account: String::from("Account 1"),
+            sweep_to: Some(String::from("Account 2")),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1, msg).unwrap();
+
+        // whoever takes the name next starts without a limit
+        let user2 = mock_info("user2", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), user2.clone(), create).unwrap();
+
+        let msg = QueryMsg::GetRateLimit {
+            account: String::from("Account 1"),
+            token: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: RateLimitResponse = from_binary(&res).unwrap();
+        assert_eq!(value.limit, None);
+        assert_eq!(value.remaining, None);
+
+        let _res = execute(deps.as_mut(), mock_env(), currency, deposit).unwrap();
+        let msg = ExecuteMsg::Withdraw {
+            account: String::from("Account 1"),
+            amount: Uint128::new(100),
+            token: None,
+            recipient: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user2, msg).unwrap();
+    }
+
+    #[test]
+    fn should_start_reused_account_name_with_empty_history() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let _res = instantiate(deps.as_mut(), mock_env(), creator, msg).unwrap();
+
+        let user1 = mock_info("user1", &[]);
+        let user2 = mock_info("user2", &[]);
+        let create = ExecuteMsg::CreateAccount {
             account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
         };
-        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+        let _res = execute(deps.as_mut(), mock_env(), user1.clone(), create.clone()).unwrap();
 
-        // user 1 deposits some money
-        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-            sender: String::from(MOCK_CONTRACT_ADDR),
-            amount: Uint128::new(55),
+        let deposit = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from("user1"),
+            amount: Uint128::new(100),
             msg: to_binary(&ReceiveMsg::Deposit {
                 account: String::from("Account 1"),
             })
             .unwrap(),
         });
-        let currency = mock_info("fake_contract", &[]);
-        let res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), deposit).unwrap();
+
+        let msg = ExecuteMsg::CloseAccount {
+            account: String::from("Account 1"),
+            sweep_to: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user1, msg).unwrap();
 
-        assert_eq!(res.is_err(), true);
+        let _res = execute(deps.as_mut(), mock_env(), user2, create).unwrap();
 
-        let msg = QueryMsg::GetBalance {
+        let history = QueryMsg::GetHistory {
             account: String::from("Account 1"),
+            start_after: None,
+            limit: None,
         };
-        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let value: BalanceResponse = from_binary(&res).unwrap();
-        assert_eq!(value.balance, Uint128::new(0));
+        let res = query(deps.as_ref(), mock_env(), history.clone()).unwrap();
+        let value: HistoryResponse = from_binary(&res).unwrap();
+        assert!(value.entries.is_empty());
+
+        let deposit = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from("user2"),
+            amount: Uint128::new(30),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let _res = execute(deps.as_mut(), mock_env(), currency, deposit).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), history).unwrap();
+        let value: HistoryResponse = from_binary(&res).unwrap();
+        assert_eq!(value.entries.len(), 1);
+        assert_eq!(value.entries[0].counterparty, "user2");
+        assert_eq!(value.entries[0].amount, Uint128::new(30));
     }
 
     #[test]
-    fn should_be_able_to_withdraw() {
+    fn should_check_reserves_of_cw20_currency() {
         let mut deps = mock_dependencies();
 
         let msg = InstantiateMsg {
             currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
         };
 
         let creator = mock_info("creator", &[]);
 
-        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        let _res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
 
-        // user 1 initializes the contract
+        for (user, name) in [("user1", "Account 1"), ("user2", "Account 2")].iter() {
+            let msg = ExecuteMsg::CreateAccount {
+                account_name: name.to_string(),
+                lock_until: None,
+                penalty: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), mock_info(user, &[]), msg).unwrap();
+        }
 
-        let user = mock_info("user1", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from("user1"),
+            amount: Uint128::new(1000),
+            msg: to_binary(&ReceiveMsg::Deposit {
+                account: String::from("Account 1"),
+            })
+            .unwrap(),
+        });
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
 
-        // user 1 adds an account
+        // the transfer fee moves 1 into the treasury
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Account 1"),
+            to: String::from("Account 2"),
+            amount: Uint128::new(100),
+            token: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("user1", &[]), msg).unwrap();
+
+        // holding only what the accounts own leaves the treasury unbacked
+        mock_cw20_balance(&mut deps, 999);
+        let msg = QueryMsg::Reserves { token: None };
+        let res = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+        let value: ReservesResponse = from_binary(&res).unwrap();
+        assert_eq!(value.liabilities, Uint128::new(999));
+        assert_eq!(value.treasury, Uint128::new(1));
+        assert_eq!(value.assets, Uint128::new(999));
+        assert_eq!(value.surplus, Uint128::zero());
+        assert!(!value.solvent);
+
+        mock_cw20_balance(&mut deps, 1050);
+        let res = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+        let value: ReservesResponse = from_binary(&res).unwrap();
+        assert_eq!(value.surplus, Uint128::new(50));
+        assert!(value.solvent);
+
+        let reconcile = ExecuteMsg::ReconcileSurplus { token: None };
+        let _res = execute(deps.as_mut(), mock_env(), creator, reconcile).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: ReservesResponse = from_binary(&res).unwrap();
+        assert_eq!(value.treasury, Uint128::new(51));
+        assert_eq!(value.surplus, Uint128::zero());
+        assert!(value.solvent);
+    }
+
+    #[test]
+    fn should_recover_cw20_currency_up_to_surplus() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let _res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
 
         let msg = ExecuteMsg::CreateAccount {
             account_name: String::from("Account 1"),
+            lock_until: None,
+            penalty: None,
         };
-        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("user1", &[]), msg).unwrap();
 
-        // user 1 deposits some money
         let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-            sender: String::from(MOCK_CONTRACT_ADDR),
-            amount: Uint128::new(55),
+            sender: String::from("user1"),
+            amount: Uint128::new(100),
             msg: to_binary(&ReceiveMsg::Deposit {
                 account: String::from("Account 1"),
             })
             .unwrap(),
         });
         let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
-        let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg);
+        let _res = execute(deps.as_mut(), mock_env(), currency, msg).unwrap();
 
-        let msg = QueryMsg::GetBalance {
-            account: String::from("Account 1"),
+        // 30 of the currency arrived by a plain cw20 transfer
+        mock_cw20_balance(&mut deps, 130);
+
+        let msg = ExecuteMsg::RecoverTokens {
+            token: Denom::Cw20(Addr::unchecked(MOCK_CONTRACT_ADDR)),
+            amount: Uint128::new(31),
+            recipient: String::from("user2"),
         };
-        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let value: BalanceResponse = from_binary(&res).unwrap();
-        assert_eq!(value.balance, Uint128::new(55));
+        let res = execute(deps.as_mut(), mock_env(), creator.clone(), msg);
+        match res {
+            Err(ContractError::ExceedsSurplus { surplus }) => {
+                assert_eq!(surplus, Uint128::new(30))
+            }
+            _ => panic!("Must return exceeds surplus error"),
+        }
 
-        // user 1 withdraws some money
+        let msg = ExecuteMsg::RecoverTokens {
+            token: Denom::Cw20(Addr::unchecked(MOCK_CONTRACT_ADDR)),
+            amount: Uint128::new(30),
+            recipient: String::from("user2"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), creator, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: String::from(MOCK_CONTRACT_ADDR),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: String::from("user2"),
+                    amount: Uint128::new(30),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn should_count_joint_account_spends_against_its_limit() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            currency: String::from(MOCK_CONTRACT_ADDR),
+            fee: None,
+        };
+
+        let creator = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), mock_env(), creator.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let alice = mock_info("alice", &[]);
+        let bob = mock_info("bob", &[]);
+
+        let msg = ExecuteMsg::CreateJointAccount {
+            account_name: String::from("Shared"),
+            signers: vec![String::from("alice"), String::from("bob")],
+            threshold: 2,
+            limit: Uint128::new(100),
+            voting_period: 3600,
+        };
+        let res = execute(deps.as_mut(), mock_env(), creator, msg.clone());
+        match res {
+            Err(ContractError::InvalidRequest(_)) => {}
+            _ => panic!("Must return invalid request error"),
+        }
+        let _res = execute(deps.as_mut(), mock_env(), alice.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CreateAccount {
+            account_name: String::from("Alice"),
+            lock_until: None,
+            penalty: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), alice.clone(), msg).unwrap();
+
+        let currency = mock_info(MOCK_CONTRACT_ADDR, &[]);
+        for account in ["Shared", "Alice"].iter() {
+            let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: String::from(MOCK_CONTRACT_ADDR),
+                amount: Uint128::new(1000),
+                msg: to_binary(&ReceiveMsg::Deposit {
+                    account: account.to_string(),
+                })
+                .unwrap(),
+            });
+            let _res = execute(deps.as_mut(), mock_env(), currency.clone(), msg).unwrap();
+        }
+
+        // two withdrawals within the limit that together exceed it
         let msg = ExecuteMsg::Withdraw {
-            account: String::from("Account 1"),
-            amount: Uint128::new(16),
+            account: String::from("Shared"),
+            amount: Uint128::new(60),
+            token: None,
+            recipient: None,
         };
-        let _res = execute(deps.as_mut(), mock_env(), user.clone(), msg);
-        println!("{:?}", _res);
+        let res = execute(deps.as_mut(), mock_env(), bob.clone(), msg.clone()).unwrap();
+        assert_eq!(1, res.messages.len());
+
+        let res = execute(deps.as_mut(), mock_env(), bob.clone(), msg.clone()).unwrap();
+        assert_eq!(0, res.messages.len());
+        assert_eq!(res.attributes[3], ("proposal_id", "1"));
+
+        // the next window starts afresh
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let res = execute(deps.as_mut(), env, bob, msg).unwrap();
+        assert_eq!(1, res.messages.len());
+
+        // the creator's own account and the joint account do not share an owner
+        let msg = ExecuteMsg::Transfer {
+            from: String::from("Alice"),
+            to: String::from("Shared"),
+            amount: Uint128::new(100),
+            token: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), alice, msg).unwrap();
+        assert_eq!(res.attributes[5], ("fee", "1"));
+
         let msg = QueryMsg::GetBalance {
-            account: String::from("Account 1"),
+            account: String::from("Shared"),
+            token: None,
         };
         let res = query(deps.as_ref(), mock_env(), msg).unwrap();
         let value: BalanceResponse = from_binary(&res).unwrap();
-        assert_eq!(value.balance, Uint128::new(39));
+        assert_eq!(value.balance, Uint128::new(979));
     }
 
     // #[test]
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{OverflowError, StdError, Timestamp, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,14 +6,116 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
     #[error("Invalid Request: {0}")]
     InvalidRequest(String),
 
+    #[error("Account {name} does not exist")]
+    AccountNotFound { name: String },
+
+    #[error("Account {name} already exists")]
+    AccountExists { name: String },
+
+    #[error("Insufficient funds: {available} available, {required} required")]
+    InsufficientFunds {
+        available: Uint128,
+        required: Uint128,
+    },
+
+    #[error("Charges of {charged} exceed the amount of {amount}")]
+    ChargesExceedAmount { amount: Uint128, charged: Uint128 },
+
+    #[error("Amount must not be zero")]
+    ZeroAmount {},
+
+    #[error("Token {token} is not accepted")]
+    UnsupportedToken { token: String },
+
+    #[error("Accounts still hold {total} of the token")]
+    OutstandingBalances { total: Uint128 },
+
+    #[error("The treasury still holds {amount} of the token")]
+    OutstandingFees { amount: Uint128 },
+
+    #[error("Currency is already {currency}")]
+    SameCurrency { currency: String },
+
+    #[error("No ownership transfer is pending")]
+    NoPendingOwner {},
+
+    #[error("The ownership proposal has expired")]
+    OwnershipProposalExpired {},
+
+    #[error("Cannot set allowance to own account")]
+    CannotSetOwnAccount {},
+
+    #[error("Allowance is expired")]
+    Expired {},
+
+    #[error("No allowance for this account")]
+    NoAllowance {},
+
+    #[error("Insufficient allowance: {allowance} allowed, {required} required")]
+    InsufficientAllowance {
+        allowance: Uint128,
+        required: Uint128,
+    },
+
+    #[error("Account {account} is controlled by its signers")]
+    JointAccount { account: String },
+
+    #[error("Amounts above {limit} need the approval of the signers")]
+    ApprovalRequired { limit: Uint128 },
+
+    #[error("Threshold {threshold} must be between 1 and the {signers} signers")]
+    InvalidThreshold { threshold: u32, signers: u32 },
+
+    #[error("Proposal already approved by this signer")]
+    AlreadyApproved {},
+
+    #[error("The proposal has expired")]
+    ProposalExpired {},
+
+    #[error("Operation {operation} is paused")]
+    Paused { operation: String },
+
+    #[error("Account {account} is frozen: {reason}")]
+    AccountFrozen { account: String, reason: String },
+
+    #[error("Withdrawal limit exceeded, {remaining} left in this window")]
+    RateLimitExceeded { remaining: Uint128 },
+
+    #[error("Account is locked until {until}")]
+    AccountLocked { until: Timestamp },
+
+    #[error("Only the surplus of {surplus} can be recovered")]
+    ExceedsSurplus { surplus: Uint128 },
+
+    #[error("Postings do not balance: {debits} debited, {credits} credited")]
+    Unbalanced { debits: Uint128, credits: Uint128 },
+
+    #[error("Cannot migrate from a different contract: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot downgrade from {stored} to {new}")]
+    CannotDowngrade { stored: String, new: String },
+
+    #[error("Semver parsing error: {0}")]
+    SemVer(String),
+
     #[error("Custom Error val: {val:?}")]
     CustomError { val: String },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
+}
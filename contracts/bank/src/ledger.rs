@@ -0,0 +1,125 @@
+use cosmwasm_std::{StdResult, Storage, Uint128};
+
+use crate::error::ContractError;
+use crate::state::{BALANCES, TOTAL_BALANCES, TREASURY};
+
+/// A book the bank keeps for each token
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Book<'a> {
+    /// Balance of a named account, owed to its owner
+    Account(&'a str),
+    /// Fees and penalties collected by the bank
+    Treasury,
+    /// Tokens entering or leaving the contract
+    External,
+}
+
+/// One side of a movement. Debits take out of an account or the treasury and
+/// credits add to them, while debiting `External` brings tokens into the
+/// contract and crediting it pays them out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Posting<'a> {
+    Debit(Book<'a>, Uint128),
+    Credit(Book<'a>, Uint128),
+}
+
+/// Applies a set of postings in `token`, refusing it unless the debits equal
+/// the credits and move something. As the only writer of account balances and
+/// the treasury, this keeps their sum equal to the tokens the contract holds.
+pub fn post(
+    storage: &mut dyn Storage,
+    token: &str,
+    postings: &[Posting],
+) -> Result<(), ContractError> {
+    let (debits, credits) = postings.iter().try_fold(
+        (Uint128::zero(), Uint128::zero()),
+        |(debits, credits), posting| -> Result<_, ContractError> {
+            Ok(match posting {
+                Posting::Debit(_, amount) => (debits.checked_add(*amount)?, credits),
+                Posting::Credit(_, amount) => (debits, credits.checked_add(*amount)?),
+            })
+        },
+    )?;
+    if debits != credits {
+        return Err(ContractError::Unbalanced { debits, credits });
+    }
+    if debits.is_zero() {
+        return Err(ContractError::ZeroAmount {});
+    }
+
+    for posting in postings {
+        match *posting {
+            Posting::Debit(book, amount) => debit(storage, token, book, amount)?,
+            Posting::Credit(book, amount) => credit(storage, token, book, amount)?,
+        }
+    }
+    Ok(())
+}
+
+fn debit(
+    storage: &mut dyn Storage,
+    token: &str,
+    book: Book,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+    match book {
+        Book::Account(account) => {
+            let balance = BALANCES
+                .may_load(storage, (account, token))?
+                .unwrap_or_default();
+            if balance < amount {
+                return Err(ContractError::InsufficientFunds {
+                    available: balance,
+                    required: amount,
+                });
+            }
+            BALANCES.save(storage, (account, token), &balance.checked_sub(amount)?)?;
+            TOTAL_BALANCES.update(storage, token, |total| -> StdResult<_> {
+                Ok(total.unwrap_or_default().checked_sub(amount)?)
+            })?;
+        }
+        Book::Treasury => {
+            let treasury = TREASURY.may_load(storage, token)?.unwrap_or_default();
+            if treasury < amount {
+                return Err(ContractError::InsufficientFunds {
+                    available: treasury,
+                    required: amount,
+                });
+            }
+            TREASURY.save(storage, token, &treasury.checked_sub(amount)?)?;
+        }
+        Book::External => {}
+    }
+    Ok(())
+}
+
+fn credit(
+    storage: &mut dyn Storage,
+    token: &str,
+    book: Book,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+    match book {
+        Book::Account(account) => {
+            BALANCES.update(storage, (account, token), |balance| -> StdResult<_> {
+                Ok(balance.unwrap_or_default().checked_add(amount)?)
+            })?;
+            TOTAL_BALANCES.update(storage, token, |total| -> StdResult<_> {
+                Ok(total.unwrap_or_default().checked_add(amount)?)
+            })?;
+        }
+        Book::Treasury => {
+            TREASURY.update(storage, token, |treasury| -> StdResult<_> {
+                Ok(treasury.unwrap_or_default().checked_add(amount)?)
+            })?;
+        }
+        Book::External => {}
+    }
+    Ok(())
+}
@@ -1,5 +1,7 @@
 pub mod contract;
 mod error;
+mod ledger;
+mod migrations;
 // pub mod helpers;
 // pub mod integration_tests;
 pub mod msg;
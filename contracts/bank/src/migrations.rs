@@ -0,0 +1,91 @@
+use cosmwasm_std::{Addr, Empty, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::Map;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContractError;
+use crate::state::{accounts, AccountData, AccountKind, BALANCES, STATE, TOKENS, TOTAL_BALANCES};
+
+type Migration = fn(&mut dyn Storage) -> StdResult<()>;
+
+/// Storage migrations in release order, each upgrading the layout of the
+/// versions before it
+const MIGRATIONS: &[(&str, Migration)] = &[("0.2.0", migrate_v0_2_0), ("0.3.0", migrate_v0_3_0)];
+
+/// Runs every migration introduced after `from`
+pub fn run(storage: &mut dyn Storage, from: &Version) -> Result<Vec<String>, ContractError> {
+    let mut applied = vec![];
+    for (version, migration) in MIGRATIONS {
+        if from < &Version::parse(version)? {
+            migration(storage)?;
+            applied.push(version.to_string());
+        }
+    }
+    Ok(applied)
+}
+
+/// Account entry of 0.1.0, holding the owner and a balance in the single currency
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LegacyBalanceData {
+    pub address: Addr,
+    pub value: Uint128,
+}
+
+pub const LEGACY_BALANCES: Map<String, LegacyBalanceData> = Map::new("balance");
+/// Account names of each owner up to 0.2.0, replaced by the owner index of `accounts`
+pub const LEGACY_OWNER: Map<&Addr, Vec<String>> = Map::new("owner");
+
+/// Splits the 0.1.0 `balance` entries into account records and per token balances
+/// in the currency
+fn migrate_v0_2_0(storage: &mut dyn Storage) -> StdResult<()> {
+    // resaving fills in the default fee schedule
+    let state = STATE.load(storage)?;
+    STATE.save(storage, &state)?;
+
+    let currency = state.currency.to_string();
+    TOKENS.save(storage, &state.currency, &Empty {})?;
+
+    let legacy = LEGACY_BALANCES
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut total = Uint128::zero();
+    for (name, data) in legacy {
+        accounts().save(
+            storage,
+            &name,
+            &AccountData {
+                address: data.address,
+                kind: AccountKind::Standard,
+            },
+        )?;
+        if !data.value.is_zero() {
+            BALANCES.save(storage, (&name, &currency), &data.value)?;
+            total = total.checked_add(data.value)?;
+        }
+        LEGACY_BALANCES.remove(storage, name);
+    }
+    TOTAL_BALANCES.save(storage, &currency, &total)?;
+
+    Ok(())
+}
+
+/// Builds the owner index of every account and drops the `owner` lists it replaces
+fn migrate_v0_3_0(storage: &mut dyn Storage) -> StdResult<()> {
+    let entries = accounts()
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (name, data) in entries {
+        // no previous data, so the index entries are written fresh
+        accounts().replace(storage, &name, Some(&data), None)?;
+    }
+
+    let owners = LEGACY_OWNER
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for owner in owners {
+        LEGACY_OWNER.remove(storage, &owner);
+    }
+
+    Ok(())
+}
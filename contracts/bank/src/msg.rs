@@ -1,13 +1,20 @@
-use cosmwasm_std::Uint128;
-use cw20::Cw20ReceiveMsg;
+use cosmwasm_std::{Addr, Binary, Decimal, Timestamp, Uint128};
+use cw20::{Cw20ReceiveMsg, Denom, Expiration};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::state::{FeeConfig, FeeTier, JointAction, JournalEntry, Operation, RateLimit, Role};
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub currency: String,
+    /// The transfer fee schedule, defaults to a flat 1%
+    pub fee: Option<FeeConfig>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
@@ -15,15 +22,66 @@ pub enum ExecuteMsg {
     CreateAccount {
         /// The account name of the account to create
         account_name: String,
+        /// Lock withdrawals and outgoing transfers until this time, making it a savings account
+        lock_until: Option<Timestamp>,
+        /// Share of the amount paid to the treasury to break the lock early, above 0
+        /// and below 1; never breakable if omitted
+        penalty: Option<Decimal>,
+    },
+    /// Create an account shared by several signers, the sender among them, which cannot be
+    /// closed or handed over
+    CreateJointAccount {
+        /// The account name of the account to create
+        account_name: String,
+        /// The addresses controlling the account
+        signers: Vec<String>,
+        /// The number of signers that must approve spending above the limit
+        threshold: u32,
+        /// The most signers can withdraw or transfer without approvals within a day
+        limit: Uint128,
+        /// The seconds a proposal stays open for approvals
+        voting_period: u64,
+    },
+    /// Approve a proposal of a joint account, executing it once the threshold is met (signers only)
+    Approve {
+        /// The id of the proposal to approve
+        proposal_id: u64,
+    },
+    /// Drop a proposal of a joint account, by one of its signers or by anyone once it expired
+    CloseProposal {
+        /// The id of the proposal to close
+        proposal_id: u64,
     },
     /// Receive forwards received cw20 tokens to an execution logic (in case of Deposit)
     Receive(Cw20ReceiveMsg),
+    /// Deposit the native coins sent along with the message
+    DepositNative {
+        /// The account to deposit to
+        account: String,
+    },
     /// Withdraw funds from an account
     Withdraw {
         /// The account to withdraw from
         account: String,
         /// The amount to withdraw
         amount: Uint128,
+        /// The token to withdraw, defaults to the currency
+        token: Option<String>,
+        /// The address to pay out to, defaults to the sender
+        recipient: Option<String>,
+    },
+    /// Withdraw funds into a contract and execute a message on it in the same transaction
+    WithdrawAndSend {
+        /// The account to withdraw from
+        account: String,
+        /// The amount to withdraw
+        amount: Uint128,
+        /// The token to withdraw, defaults to the currency
+        token: Option<String>,
+        /// The contract receiving the funds
+        contract: String,
+        /// The message executed on the contract, e.g. a cw20 receive hook
+        msg: Binary,
     },
     /// Transfer funds from one account to another
     Transfer {
@@ -33,12 +91,215 @@ pub enum ExecuteMsg {
         to: String,
         /// The amount to transfer
         amount: Uint128,
+        /// The token to transfer, defaults to the currency
+        token: Option<String>,
+    },
+    /// Allow a spender to withdraw or transfer more out of an account (account owner only)
+    IncreaseAllowance {
+        /// The account to spend from
+        account: String,
+        /// The address allowed to spend
+        spender: String,
+        /// The amount added to the allowance
+        amount: Uint128,
+        /// The token of the allowance, defaults to the currency
+        token: Option<String>,
+        /// When the allowance lapses, kept as is if omitted
+        expires: Option<Expiration>,
+    },
+    /// Lower the allowance of a spender, removing it once it reaches zero (account owner only)
+    DecreaseAllowance {
+        /// The account to spend from
+        account: String,
+        /// The address allowed to spend
+        spender: String,
+        /// The amount taken off the allowance
+        amount: Uint128,
+        /// The token of the allowance, defaults to the currency
+        token: Option<String>,
+        /// When the allowance lapses, kept as is if omitted
+        expires: Option<Expiration>,
+    },
+    /// Transfer funds out of an account using the allowance granted to the sender
+    TransferFrom {
+        /// The account to transfer from
+        from: String,
+        /// The account to transfer to
+        to: String,
+        /// The amount to transfer
+        amount: Uint128,
+        /// The token to transfer, defaults to the currency
+        token: Option<String>,
+    },
+    /// Withdraw funds from an account using the allowance granted to the sender
+    WithdrawFrom {
+        /// The account to withdraw from
+        account: String,
+        /// The amount to withdraw
+        amount: Uint128,
+        /// The token to withdraw, defaults to the currency
+        token: Option<String>,
+        /// The address to pay out to, defaults to the sender
+        recipient: Option<String>,
+    },
+    /// Delete an account, withdrawing what it holds or moving it to another account
+    CloseAccount {
+        /// The account to close
+        account: String,
+        /// The account receiving the remaining balances, they are withdrawn to the sender if omitted
+        sweep_to: Option<String>,
     },
-    /// Change the currency of an account
+    /// Offer an account to another address, replacing any previous offer (account owner only)
+    TransferAccountOwnership {
+        /// The account to hand over
+        account: String,
+        /// The address to hand the account to
+        new_owner: String,
+    },
+    /// Take over an account offered to the sender
+    AcceptAccountOwnership {
+        /// The account to take over
+        account: String,
+    },
+    /// Withdraw a pending account offer (account owner only)
+    CancelAccountOwnershipTransfer {
+        /// The account offered
+        account: String,
+    },
+    /// Change the default currency, accepting it for deposits (admin only)
     ChangeCurrency {
         /// The new currency
         currency: String,
     },
+    /// Accept deposits of another cw20 token (admin only)
+    AddToken {
+        /// The cw20 contract address
+        token: String,
+    },
+    /// Accept deposits of a native coin (admin only)
+    AddNativeToken {
+        /// The native denom, e.g. uluna
+        denom: String,
+    },
+    /// Stop accepting a token nobody holds anymore (admin only)
+    RemoveToken {
+        /// The cw20 contract address or native denom
+        token: String,
+    },
+    /// Pay out collected transfer fees from the treasury (fee manager only)
+    WithdrawFees {
+        /// The address to send the fees to
+        recipient: String,
+        /// The amount to withdraw
+        amount: Uint128,
+        /// The token to withdraw, defaults to the currency
+        token: Option<String>,
+    },
+    /// Move tokens the contract holds beyond the account balances and the
+    /// treasury into the treasury (admin only)
+    ReconcileSurplus {
+        /// The token to reconcile, defaults to the currency
+        token: Option<String>,
+    },
+    /// Return tokens sent to the contract without a deposit (admin only).
+    /// Accepted tokens can only be recovered up to the surplus over what the
+    /// bank owes, any other token in full.
+    RecoverTokens {
+        /// The native denom or cw20 contract to recover
+        token: Denom,
+        /// The amount to recover
+        amount: Uint128,
+        /// The address to send the tokens to
+        recipient: String,
+    },
+    /// Replace the transfer fee schedule (fee manager only)
+    UpdateFeeConfig {
+        /// The new fee schedule
+        fee: FeeConfig,
+    },
+    /// Grant an account a fee tier, or remove it with `None` (fee manager only)
+    SetAccountFeeTier {
+        /// The account to grant the tier to
+        account: String,
+        /// The tier to grant
+        tier: Option<FeeTier>,
+    },
+    /// Grant every account of an owner a fee tier, or remove it with `None` (fee manager only)
+    SetOwnerFeeTier {
+        /// The address owning the accounts
+        owner: String,
+        /// The tier to grant
+        tier: Option<FeeTier>,
+    },
+    /// Offer the ownership of the bank to another address (owner only)
+    ProposeOwner {
+        /// The address to hand ownership to
+        new_owner: String,
+        /// When the offer lapses, never if omitted
+        expiry: Option<Expiration>,
+    },
+    /// Accept a pending ownership offer (proposed owner only)
+    AcceptOwnership {},
+    /// Withdraw a pending ownership offer (owner only)
+    CancelOwnershipProposal {},
+    /// Give up ownership, disabling every owner only message (owner only)
+    RenounceOwnership {},
+    /// Halt operations, every operation if none are given (pauser only)
+    Pause {
+        /// The operations to halt
+        operations: Option<Vec<Operation>>,
+    },
+    /// Resume operations, every operation if none are given (pauser only)
+    Unpause {
+        /// The operations to resume
+        operations: Option<Vec<Operation>>,
+    },
+    /// Block withdrawals and outgoing transfers of an account (compliance only)
+    FreezeAccount {
+        /// The account to freeze
+        account: String,
+        /// Why the account is held, reported in errors
+        reason: String,
+        /// When the hold lifts by itself, never if omitted
+        expires: Option<Expiration>,
+    },
+    /// Lift the hold on an account (compliance only)
+    UnfreezeAccount {
+        /// The account to unfreeze
+        account: String,
+    },
+    /// Cap what an account withdraws or transfers to other owners per window,
+    /// or lift the cap with `None` (admin only)
+    SetAccountRateLimit {
+        /// The account to limit
+        account: String,
+        /// The token of the limit, defaults to the currency
+        token: Option<String>,
+        /// The new limit
+        limit: Option<RateLimit>,
+    },
+    /// Cap what is withdrawn from the bank per window across all accounts,
+    /// or lift the cap with `None` (admin only)
+    SetGlobalRateLimit {
+        /// The token of the limit, defaults to the currency
+        token: Option<String>,
+        /// The new limit
+        limit: Option<RateLimit>,
+    },
+    /// Grant a role to an address (owner only)
+    GrantRole {
+        /// The role to grant
+        role: Role,
+        /// The address receiving the role
+        address: String,
+    },
+    /// Take a role away from an address (owner only)
+    RevokeRole {
+        /// The role to revoke
+        role: Role,
+        /// The address holding the role
+        address: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -52,6 +313,107 @@ pub enum QueryMsg {
     GetBalance {
         /// The account to get the balance of
         account: String,
+        /// The token to get the balance in, all holdings are returned when omitted
+        token: Option<String>,
+    },
+    /// GetHistory returns the deposits, withdrawals and transfers of an account, newest first
+    GetHistory {
+        /// The account to get the statement of
+        account: String,
+        /// The entry id to continue before
+        start_after: Option<u64>,
+        /// The maximum number of entries to return
+        limit: Option<u32>,
+    },
+    /// GetTreasury returns the transfer fees collected so far
+    GetTreasury {
+        /// The token the fees were collected in, defaults to the currency
+        token: Option<String>,
+    },
+    /// Reserves compares the tokens the contract holds with what it owes
+    Reserves {
+        /// The token to check, defaults to the currency
+        token: Option<String>,
+    },
+    /// GetConfig returns the owner, currency and active fee schedule
+    GetConfig {},
+    /// GetTokens returns the cw20 contracts and native denoms accepted for deposits
+    GetTokens {},
+    /// GetOwnership returns the owner and any pending ownership offer
+    GetOwnership {},
+    /// GetPauseState returns which operations are currently halted
+    GetPauseState {},
+    /// ListFrozenAccounts returns the accounts under a hold, in name order
+    ListFrozenAccounts {
+        /// The account name to continue after
+        start_after: Option<String>,
+        /// The maximum number of accounts to return
+        limit: Option<u32>,
+    },
+    /// GetRateLimit returns the withdrawal limits of an account and what is left of them
+    GetRateLimit {
+        /// The limited account
+        account: String,
+        /// The token of the limits, defaults to the currency
+        token: Option<String>,
+    },
+    /// Roles returns the addresses granted each role
+    Roles {
+        /// The role to list, all roles if omitted
+        role: Option<Role>,
+    },
+    /// ListAccounts returns every account with its owner and balances, in name order
+    ListAccounts {
+        /// The account name to continue after
+        start_after: Option<String>,
+        /// The maximum number of accounts to return
+        limit: Option<u32>,
+    },
+    /// ListAccountsByOwner returns the accounts of an address with their balances
+    ListAccountsByOwner {
+        /// The owner address of the accounts
+        owner: String,
+        /// The account name to continue after
+        start_after: Option<String>,
+        /// The maximum number of accounts to return
+        limit: Option<u32>,
+    },
+    /// GetJointAccount returns the signers, threshold and limit of a joint account
+    GetJointAccount {
+        /// The joint account
+        account: String,
+    },
+    /// GetProposal returns a proposal still waiting for approvals
+    GetProposal {
+        /// The id of the proposal
+        proposal_id: u64,
+    },
+    /// ListProposals returns the proposals still waiting for approvals, oldest first
+    ListProposals {
+        /// The proposal id to continue after
+        start_after: Option<u64>,
+        /// The maximum number of proposals to return
+        limit: Option<u32>,
+    },
+    /// Allowance returns what a spender may still take out of an account
+    Allowance {
+        /// The account to spend from
+        account: String,
+        /// The address allowed to spend
+        spender: String,
+        /// The token of the allowance, defaults to the currency
+        token: Option<String>,
+    },
+    /// AllAllowances returns the allowances granted on an account in one token, in spender order
+    AllAllowances {
+        /// The account to spend from
+        account: String,
+        /// The token of the allowances, defaults to the currency
+        token: Option<String>,
+        /// The spender to continue after
+        start_after: Option<String>,
+        /// The maximum number of allowances to return
+        limit: Option<u32>,
     },
 }
 
@@ -63,7 +425,133 @@ pub struct AccountResponse {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BalanceResponse {
+    /// Balance in the requested token, or in the currency when none was requested
     pub balance: Uint128,
+    pub holdings: Vec<TokenBalance>,
+    /// When a time-locked account unlocks
+    pub locked_until: Option<Timestamp>,
+    /// Whether the lock is still in force
+    pub locked: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenBalance {
+    pub token: String,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HistoryResponse {
+    pub entries: Vec<JournalEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TreasuryResponse {
+    pub balance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReservesResponse {
+    pub token: String,
+    /// Sum of all account balances
+    pub liabilities: Uint128,
+    /// Fees and penalties collected by the bank
+    pub treasury: Uint128,
+    /// Tokens held by the contract
+    pub assets: Uint128,
+    /// Tokens held beyond the liabilities and the treasury, such as direct transfers
+    pub surplus: Uint128,
+    /// Whether the assets cover the liabilities and the treasury
+    pub solvent: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccountInfo {
+    pub name: String,
+    pub owner: Addr,
+    /// The address the account is offered to, if any
+    pub pending_owner: Option<Addr>,
+    pub balances: Vec<TokenBalance>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ListAccountsResponse {
+    pub accounts: Vec<AccountInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokensResponse {
+    pub tokens: Vec<Addr>,
+    pub native: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: Option<Addr>,
+    pub currency: Addr,
+    pub fee: FeeConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct JointAccountResponse {
+    pub signers: Vec<Addr>,
+    pub threshold: u32,
+    pub limit: Uint128,
+    pub voting_period: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProposalResponse {
+    pub id: u64,
+    pub account: String,
+    pub action: JointAction,
+    pub approvals: Vec<Addr>,
+    pub expires: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ListProposalsResponse {
+    pub proposals: Vec<ProposalResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FrozenAccount {
+    pub account: String,
+    pub reason: String,
+    pub expires: Option<Expiration>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FrozenAccountsResponse {
+    pub accounts: Vec<FrozenAccount>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RateLimitResponse {
+    pub limit: Option<RateLimit>,
+    /// What the account can still move out in the current window
+    pub remaining: Option<Uint128>,
+    pub global_limit: Option<RateLimit>,
+    /// What can still leave the bank in the current window
+    pub global_remaining: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoleGrant {
+    pub role: Role,
+    pub address: Addr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RolesResponse {
+    pub grants: Vec<RoleGrant>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnershipResponse {
+    pub owner: Option<Addr>,
+    pub pending_owner: Option<Addr>,
+    pub pending_expiry: Option<Expiration>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -1,21 +1,352 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Binary, BlockInfo, Decimal, Empty, Timestamp, Uint128};
+use cw20::{AllowanceResponse, Expiration};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
-    pub owner: Addr,
+    /// The bank admin, `None` once ownership has been renounced
+    pub owner: Option<Addr>,
+    /// The token used when a request does not name one
     pub currency: Addr,
+    #[serde(default)]
+    pub fee: FeeConfig,
+    /// Operations halted by a pauser
+    #[serde(default)]
+    pub paused: PauseState,
 }
 
+/// Circuit breaker of each operation users can perform
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct PauseState {
+    pub deposits: bool,
+    pub withdrawals: bool,
+    pub transfers: bool,
+    pub account_creation: bool,
+}
+
+impl PauseState {
+    pub fn is_paused(&self, operation: &Operation) -> bool {
+        match operation {
+            Operation::Deposits => self.deposits,
+            Operation::Withdrawals => self.withdrawals,
+            Operation::Transfers => self.transfers,
+            Operation::AccountCreation => self.account_creation,
+        }
+    }
+
+    pub fn set(&mut self, operation: &Operation, paused: bool) {
+        match operation {
+            Operation::Deposits => self.deposits = paused,
+            Operation::Withdrawals => self.withdrawals = paused,
+            Operation::Transfers => self.transfers = paused,
+            Operation::AccountCreation => self.account_creation = paused,
+        }
+    }
+}
+
+/// The operations that can be paused, closing an account counts as a
+/// withdrawal or, when sweeping into another account, a transfer
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Deposits,
+    Withdrawals,
+    Transfers,
+    AccountCreation,
+}
+
+impl Operation {
+    pub fn all() -> Vec<Operation> {
+        vec![
+            Operation::Deposits,
+            Operation::Withdrawals,
+            Operation::Transfers,
+            Operation::AccountCreation,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Deposits => "deposits",
+            Operation::Withdrawals => "withdrawals",
+            Operation::Transfers => "transfers",
+            Operation::AccountCreation => "account_creation",
+        }
+    }
+}
+
+/// Fee charged on transfers between accounts of different owners
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeConfig {
+    /// Fraction of the transferred amount taken as fee, e.g. 0.01 for 1%
+    pub rate: Decimal,
+    /// Lower bound of the fee, never more than the transferred amount
+    pub min: Option<Uint128>,
+    /// Upper bound of the fee
+    pub max: Option<Uint128>,
+    /// Rates replacing `rate` for larger transfers
+    #[serde(default)]
+    pub bands: Vec<FeeBand>,
+}
+
+/// Rate applied to transfers of at least `from`, the highest matching band wins
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeBand {
+    pub from: Uint128,
+    pub rate: Decimal,
+}
+
+/// Discounted rate granted by the owner to an account or owner address,
+/// a zero rate makes it fee exempt
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct BalanceData {
+pub struct FeeTier {
+    /// Label reported in the `fee_tier` attribute of transfers
+    pub name: String,
+    pub rate: Decimal,
+}
+
+impl Default for FeeConfig {
+    /// The flat 1% fee the bank has always charged
+    fn default() -> Self {
+        FeeConfig {
+            rate: Decimal::percent(1),
+            min: None,
+            max: None,
+            bands: vec![],
+        }
+    }
+}
+
+/// Ownership offered by the owner, taking effect once `new_owner` accepts it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingOwner {
+    pub new_owner: Addr,
+    pub expiry: Option<Expiration>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccountData {
     pub address: Addr,
-    pub value: Uint128,
+    #[serde(default)]
+    pub kind: AccountKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountKind {
+    Standard,
+    /// Savings account whose funds cannot leave before `lock_until`
+    TimeLocked {
+        lock_until: Timestamp,
+        /// Share of the amount moved to the treasury to leave early, the lock
+        /// cannot be broken if omitted
+        penalty: Option<Decimal>,
+    },
+}
+
+impl Default for AccountKind {
+    /// Accounts written before savings accounts existed
+    fn default() -> Self {
+        AccountKind::Standard
+    }
+}
+
+/// Signers sharing control of an account, after a cw3 fixed multisig
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct JointAccount {
+    pub signers: Vec<Addr>,
+    /// Approvals needed to execute a proposal
+    pub threshold: u32,
+    /// Largest amount a single signer can move without a proposal
+    pub limit: Uint128,
+    /// Seconds a proposal stays open for approvals
+    pub voting_period: u64,
+}
+
+/// Spending of a joint account waiting for the approval of its signers
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Proposal {
+    pub account: String,
+    pub action: JointAction,
+    pub approvals: Vec<Addr>,
+    /// Approvals are refused after this, leaving the proposal to be closed
+    pub expires: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JointAction {
+    Withdraw {
+        amount: Uint128,
+        token: String,
+        recipient: Addr,
+    },
+    Transfer {
+        to: String,
+        amount: Uint128,
+        token: String,
+    },
+    WithdrawAndSend {
+        amount: Uint128,
+        token: String,
+        contract: Addr,
+        msg: Binary,
+    },
+}
+
+/// Privileges the owner can hand out, the owner holds all of them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Manages the currency and accepted tokens
+    Admin,
+    /// Manages the fee schedule, fee tiers and the treasury
+    FeeManager,
+    /// Halts and resumes operations
+    Pauser,
+    /// Places compliance holds on accounts
+    Compliance,
+}
+
+impl Role {
+    pub fn all() -> Vec<Role> {
+        vec![
+            Role::Admin,
+            Role::FeeManager,
+            Role::Pauser,
+            Role::Compliance,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::FeeManager => "fee_manager",
+            Role::Pauser => "pauser",
+            Role::Compliance => "compliance",
+        }
+    }
+}
+
+/// Compliance hold blocking the outflows of an account
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Freeze {
+    pub reason: String,
+    /// When the hold lifts by itself, never if omitted
+    pub expires: Option<Expiration>,
+}
+
+impl Freeze {
+    pub fn is_expired(&self, block: &BlockInfo) -> bool {
+        matches!(self.expires, Some(expires) if expires.is_expired(block))
+    }
+}
+
+/// Most that can flow out in one window
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RateLimit {
+    pub amount: Uint128,
+    /// Length of the window in seconds of block time, e.g. 86400 for a daily limit
+    pub window: u64,
+}
+
+/// Outflow counted since the current window started
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WindowUsage {
+    pub start: Timestamp,
+    pub spent: Uint128,
+}
+
+/// A line on the statement of an account
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct JournalEntry {
+    /// Increases with every entry across all accounts
+    pub id: u64,
+    pub kind: EntryKind,
+    /// The depositor, the recipient of a withdrawal or the other account of a transfer
+    pub counterparty: String,
+    pub token: String,
+    /// What entered or left the account
+    pub amount: Uint128,
+    /// Part of `amount` kept by the bank as fee or early break penalty
+    pub fee: Uint128,
+    pub height: u64,
+    pub time: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Deposit,
+    Withdrawal,
+    TransferIn,
+    TransferOut,
 }
 
 pub const STATE: Item<State> = Item::new("state");
-pub const BALANCES: Map<String, BalanceData> = Map::new("balance");
-pub const OWNER: Map<&Addr, Vec<String>> = Map::new("owner");
+pub const PENDING_OWNER: Item<PendingOwner> = Item::new("pending_owner");
+/// Addresses granted each role, keyed by role name
+pub const ROLES: Map<(&str, &Addr), Empty> = Map::new("role");
+/// Account ownership offered by the owner of an account, keyed by account name
+pub const PENDING_ACCOUNT_OWNERS: Map<&str, Addr> = Map::new("pending_account_owner");
+/// Balance of every account, keyed by account name and cw20 contract address or native denom
+pub const BALANCES: Map<(&str, &str), Uint128> = Map::new("account_balance");
+/// The cw20 contracts accepted for deposits
+pub const TOKENS: Map<&Addr, Empty> = Map::new("token");
+/// The native denoms accepted for deposits
+pub const NATIVE_TOKENS: Map<&str, Empty> = Map::new("native_token");
+pub const ACCOUNT_FEE_TIERS: Map<&str, FeeTier> = Map::new("account_fee_tier");
+pub const OWNER_FEE_TIERS: Map<&Addr, FeeTier> = Map::new("owner_fee_tier");
+/// What a spender may still take out of an account, keyed by account name, token and spender
+pub const ALLOWANCES: Map<(&str, &str, &Addr), AllowanceResponse> = Map::new("allowance");
+pub const JOINT_ACCOUNTS: Map<&str, JointAccount> = Map::new("joint_account");
+/// What signers spent out of each joint account without approvals in the current window
+pub const JOINT_USAGE: Map<&str, WindowUsage> = Map::new("joint_usage");
+/// Pending proposals of joint accounts by id, removed once executed or closed
+pub const PROPOSALS: Map<u64, Proposal> = Map::new("proposal");
+pub const PROPOSAL_COUNT: Item<u64> = Item::new("proposal_count");
+/// Accounts under a compliance hold, keyed by account name
+pub const FROZEN: Map<&str, Freeze> = Map::new("frozen_account");
+/// Withdrawal limits keyed by account name and token
+pub const ACCOUNT_RATE_LIMITS: Map<(&str, &str), RateLimit> = Map::new("account_rate_limit");
+pub const ACCOUNT_USAGE: Map<(&str, &str), WindowUsage> = Map::new("account_usage");
+/// Limits on what leaves the bank in each token across all accounts
+pub const GLOBAL_RATE_LIMITS: Map<&str, RateLimit> = Map::new("global_rate_limit");
+pub const GLOBAL_USAGE: Map<&str, WindowUsage> = Map::new("global_usage");
+/// Statement of every account, keyed by account name and entry id
+pub const JOURNAL: Map<(&str, u64), JournalEntry> = Map::new("journal");
+pub const JOURNAL_SEQ: Item<u64> = Item::new("journal_seq");
+/// Last entry id when each account was opened, the entries up to it belong to
+/// a closed account of the same name
+pub const JOURNAL_START: Map<&str, u64> = Map::new("journal_start");
+/// Sum of all `BALANCES` entries of each token
+pub const TOTAL_BALANCES: Map<&str, Uint128> = Map::new("total_balance");
+/// Transfer fees collected by the bank in each token, withdrawable by the owner
+pub const TREASURY: Map<&str, Uint128> = Map::new("fee_treasury");
+
+pub struct AccountIndexes<'a> {
+    /// The accounts of each owner address
+    pub address: MultiIndex<'a, Addr, AccountData, String>,
+}
+
+impl<'a> IndexList<AccountData> for AccountIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<AccountData>> + '_> {
+        let v: Vec<&dyn Index<AccountData>> = vec![&self.address];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Every account by name, indexed by owner address
+pub fn accounts<'a>() -> IndexedMap<'a, &'a str, AccountData, AccountIndexes<'a>> {
+    let indexes = AccountIndexes {
+        address: MultiIndex::new(
+            |data: &AccountData| data.address.clone(),
+            "account",
+            "account__address",
+        ),
+    };
+    IndexedMap::new("account", indexes)
+}